@@ -1,10 +1,11 @@
 extern crate kern;
 
+use kern::Result;
 use kern::http::name;
-use kern::http::server::{HttpRequest, HttpServerBuilder, load_certificate_provider};
+use kern::http::server::load_certificate_provider;
+use kern::http::server::{ErrorContext, HttpError, HttpRequest, HttpServerBuilder};
 use kern::http::server::{HttpSettings, ResponseData, respond};
 use kern::meta::version;
-use kern::{Error, Fail, Result};
 use std::fs::File;
 use std::io::prelude::Read;
 use std::sync::RwLock;
@@ -30,26 +31,26 @@ fn handler(req: HttpRequest) -> Result<Vec<u8>> {
     *num += 1;
     dbg!(*num);
     println!("New request from IP: {}", req.ip());
-    let filename = req
-        .get()
-        .get("file")
-        .ok_or_else(|| Fail::new("filename missing, try adding ?file=... to the url"))?;
-    let mut file = File::open(filename)?;
+    let filename = req.get().get("file").ok_or_else(|| {
+        HttpError::bad_request("filename missing, try adding ?file=... to the url")
+    })?;
+    let mut file =
+        File::open(filename).map_err(|_| HttpError::boxed("404 Not Found", "file not found"))?;
     let mut buf = String::new();
     file.read_to_string(&mut buf)?;
     Ok(respond(buf, "text/html", None))
 }
 
-fn error_handler(err: Error) -> Vec<u8> {
+fn error_handler(context: ErrorContext) -> Vec<u8> {
     let msg = format!(
         "<!DOCTYPE html><html><head><title>{0}</title></head><body><h3>Fileserver error</h3><p>{0}</p><hr><address>{1} v{2}</address></body></html>",
-        err,
+        context.public_message(),
         name(),
         version()
     );
     respond(
         msg.into_bytes(),
         "text/html",
-        ResponseData::bad_request().build(),
+        ResponseData::new().status(context.status).build(),
     )
 }
@@ -1,5 +1,10 @@
 //! Byte-level operations
 
+use crate::{Fail, Result};
+
+/// Standard base64 alphabet (RFC 4648)
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
 /// Split bytes at most n times
 pub fn splitn<D: AsRef<[u8]>>(n: usize, data: &D, seperator: impl AsRef<[u8]>) -> Vec<&[u8]> {
     // as ref
@@ -82,3 +87,67 @@ pub fn scan(data: impl AsRef<[u8]>, pattern: impl AsRef<[u8]>) -> Option<usize>
     // not found
     None
 }
+
+/// Encode bytes as padded base64 (RFC 4648)
+pub fn base64_encode(data: impl AsRef<[u8]>) -> String {
+    let data = data.as_ref();
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    // encode 3 bytes to 4 characters
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decode base64 with optional padding (RFC 4648)
+pub fn base64_decode(data: impl AsRef<[u8]>) -> Result<Vec<u8>> {
+    // strip padding
+    let data = data.as_ref();
+    let padding = data.iter().rev().take_while(|&&b| b == b'=').count();
+    if padding > 2 || (padding > 0 && data.len() % 4 != 0) {
+        return Fail::from("invalid base64 padding");
+    }
+    let data = &data[..data.len() - padding];
+    if data.len() % 4 == 1 {
+        return Fail::from("invalid base64 length");
+    }
+
+    // decode 4 characters to 3 bytes
+    let mut decoded = Vec::with_capacity(data.len() * 3 / 4);
+    for chunk in data.chunks(4) {
+        let mut n = 0u32;
+        for &c in chunk {
+            let value = match c {
+                b'A'..=b'Z' => c - b'A',
+                b'a'..=b'z' => c - b'a' + 26,
+                b'0'..=b'9' => c - b'0' + 52,
+                b'+' => 62,
+                b'/' => 63,
+                _ => return Fail::from("invalid base64 character"),
+            };
+            n = n << 6 | value as u32;
+        }
+        n <<= 6 * (4 - chunk.len());
+        decoded.extend_from_slice(&n.to_be_bytes()[1..chunk.len()]);
+    }
+    Ok(decoded)
+}
+
+/// Compare bytes in constant time (for equal lengths)
+pub fn constant_time_eq(a: impl AsRef<[u8]>, b: impl AsRef<[u8]>) -> bool {
+    let (a, b) = (a.as_ref(), b.as_ref());
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
@@ -0,0 +1,161 @@
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::io::Read;
+use std::time::Duration;
+
+use crate::Result;
+use crate::http::common::HttpMethod;
+
+use super::client::HttpClient;
+use super::multipart::MultipartForm;
+use super::request::Body;
+use super::response::{HttpResponse, StreamingResponse};
+use super::timeout::Timeouts;
+
+/// Builder for a single request of HttpClient
+pub struct RequestBuilder<'a> {
+    client: &'a HttpClient,
+    method: HttpMethod,
+    url: String,
+    body: Payload<'a>,
+    options: RequestOptions,
+}
+
+/// Headers and timeouts of a single request
+#[derive(Clone, Debug)]
+pub(crate) struct RequestOptions {
+    pub headers: Vec<(String, String)>,
+    pub timeouts: Timeouts,
+}
+
+impl RequestOptions {
+    /// Create new RequestOptions without headers
+    pub fn new(timeouts: Timeouts) -> Self {
+        Self {
+            headers: Vec::new(),
+            timeouts,
+        }
+    }
+}
+
+/// Owned request body
+enum Payload<'a> {
+    Empty,
+    Bytes(Vec<u8>),
+    Reader(Box<dyn Read + 'a>, Option<u64>),
+}
+
+impl<'a> RequestBuilder<'a> {
+    /// Create new RequestBuilder with timeouts of client
+    pub(crate) fn new(
+        client: &'a HttpClient,
+        method: HttpMethod,
+        url: String,
+        timeouts: Timeouts,
+    ) -> Self {
+        Self {
+            client,
+            method,
+            url,
+            body: Payload::Empty,
+            options: RequestOptions::new(timeouts),
+        }
+    }
+
+    /// Add header, replaces headers of client with same name
+    pub fn header(mut self, name: impl ToString, value: impl ToString) -> Self {
+        self.options
+            .headers
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Set request body
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Payload::Bytes(body.into());
+        self
+    }
+
+    /// Stream request body from reader, chunked if length is unknown
+    pub fn body_reader(mut self, reader: impl Read + 'a, length: Option<u64>) -> Self {
+        self.body = Payload::Reader(Box::new(reader), length);
+        self
+    }
+
+    /// Send multipart/form-data body, sets Content-Type header
+    pub fn multipart(self, form: MultipartForm<'a>) -> Self {
+        let length = form.length();
+        self.header("Content-Type", form.content_type())
+            .body_reader(form.into_reader(), length)
+    }
+
+    /// Override all timeouts
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.options.timeouts = timeouts;
+        self
+    }
+
+    /// Override connect timeout
+    pub fn connect_timeout(mut self, connect: Option<Duration>) -> Self {
+        self.options.timeouts.connect = connect;
+        self
+    }
+
+    /// Override read timeout
+    pub fn read_timeout(mut self, read: Option<Duration>) -> Self {
+        self.options.timeouts.read = read;
+        self
+    }
+
+    /// Override write timeout
+    pub fn write_timeout(mut self, write: Option<Duration>) -> Self {
+        self.options.timeouts.write = write;
+        self
+    }
+
+    /// Override total timeout
+    pub fn total_timeout(mut self, total: Option<Duration>) -> Self {
+        self.options.timeouts.total = total;
+        self
+    }
+
+    /// Send request
+    pub fn send(mut self) -> Result<HttpResponse> {
+        let body = self.body.borrow();
+        self.client
+            .send(self.method, &self.url, body, &self.options)
+    }
+
+    /// Send request, body of response is read on demand
+    pub fn stream(mut self) -> Result<StreamingResponse<'a>> {
+        let body = self.body.borrow();
+        self.client
+            .stream(self.method, &self.url, body, &self.options)
+    }
+}
+
+impl Payload<'_> {
+    fn borrow(&mut self) -> Body<'_> {
+        match self {
+            Payload::Empty => Body::Empty,
+            Payload::Bytes(bytes) => Body::Bytes(bytes),
+            Payload::Reader(reader, length) => Body::Reader(reader, *length),
+        }
+    }
+}
+
+impl Debug for RequestBuilder<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let body = match &self.body {
+            Payload::Empty => "empty".to_string(),
+            Payload::Bytes(bytes) => format!("{} bytes", bytes.len()),
+            Payload::Reader(_, Some(length)) => format!("reader of {length} bytes"),
+            Payload::Reader(_, None) => "reader".to_string(),
+        };
+        f.debug_struct("RequestBuilder")
+            .field("method", &self.method)
+            .field("url", &self.url)
+            .field("body", &body)
+            .field("options", &self.options)
+            .finish()
+    }
+}
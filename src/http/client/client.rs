@@ -1,17 +1,27 @@
 use core::str;
 use std::collections::HashMap;
-use std::io::Write;
+use std::io::{Write, copy, sink};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::Result;
-use crate::http::common::HttpMethod;
+use crate::http::common::{HttpMethod, current_request_id};
+use crate::http::url::Url;
+use crate::{Fail, Result};
 
-use super::request::{connect, end_headers, send_content_length, send_header, send_main_header};
-use super::response::{HttpResponse, read_all};
+use super::builder::{RequestBuilder, RequestOptions};
+use super::cookie::CookieJar;
+use super::pool::{ConnectionPool, PooledConnection};
+use super::proxy::{Proxy, ProxyConfig};
+use super::request::{
+    Body, connect, connection_key, end_headers, pool_key, send_body, send_framing, send_header,
+    send_main_header,
+};
+use super::response::{HttpResponse, ResponseHead, StreamingResponse, read_head};
+use super::timeout::{TimeoutError, Timeouts, is_timeout_error};
 
 #[cfg(feature = "tls")]
 use {
     rustls::{ClientConfig, RootCertStore},
-    std::sync::Arc,
     webpki_roots::TLS_SERVER_ROOTS,
 };
 
@@ -19,10 +29,31 @@ use {
 pub struct HttpClient {
     headers: HashMap<String, Vec<String>>,
     query: HashMap<String, String>,
+    pool: ConnectionPool,
+    redirect_policy: RedirectPolicy,
+    timeouts: Timeouts,
+    cookie_jar: Option<Arc<CookieJar>>,
+    proxy: ProxyConfig,
     #[cfg(feature = "tls")]
     config: Arc<ClientConfig>,
 }
 
+/// Whether and how far HttpClient follows redirects
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RedirectPolicy {
+    /// Return redirect responses as-is
+    #[default]
+    None,
+    /// Follow at most this many redirects, fail if exceeded
+    Limit(usize),
+}
+
+/// Default maximum number of idle connections per host
+const MAX_IDLE: usize = 8;
+
+/// Default time idle connections are kept
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
 impl HttpClient {
     pub fn new() -> Self {
         let headers = HashMap::new();
@@ -38,6 +69,11 @@ impl HttpClient {
         Self {
             headers,
             query,
+            pool: ConnectionPool::new(MAX_IDLE, IDLE_TIMEOUT),
+            redirect_policy: RedirectPolicy::None,
+            timeouts: Timeouts::new(),
+            cookie_jar: None,
+            proxy: ProxyConfig::new(),
             #[cfg(feature = "tls")]
             config,
         }
@@ -90,12 +126,77 @@ impl HttpClient {
         &mut self.query
     }
 
+    /// Keep at most max_idle connections per host alive (0 disables keep-alive)
+    pub fn with_max_idle(mut self, max_idle: usize) -> Self {
+        self.pool = ConnectionPool::new(max_idle, self.pool.idle_timeout());
+        self
+    }
+
+    /// Close connections idle for longer than idle_timeout
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.pool = ConnectionPool::new(self.pool.max_idle(), idle_timeout);
+        self
+    }
+
+    pub fn pool(&self) -> &ConnectionPool {
+        &self.pool
+    }
+
+    /// Follow redirects according to policy
+    pub fn with_redirect_policy(mut self, redirect_policy: RedirectPolicy) -> Self {
+        self.redirect_policy = redirect_policy;
+        self
+    }
+
+    pub fn redirect_policy(&self) -> RedirectPolicy {
+        self.redirect_policy
+    }
+
+    /// Store cookies of responses in jar and send them with requests
+    ///
+    /// A Cookie header set on client or request replaces cookies of the jar
+    pub fn with_cookie_jar(mut self, cookie_jar: Arc<CookieJar>) -> Self {
+        self.cookie_jar = Some(cookie_jar);
+        self
+    }
+
+    pub fn cookie_jar(&self) -> Option<&Arc<CookieJar>> {
+        self.cookie_jar.as_ref()
+    }
+
+    /// Send requests through forward proxies (e.g. ProxyConfig::from_env())
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    pub fn proxy(&self) -> &ProxyConfig {
+        &self.proxy
+    }
+
+    /// Set default timeouts of requests
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    pub fn timeouts(&self) -> Timeouts {
+        self.timeouts
+    }
+
+    #[cfg(feature = "tls")]
+    /// Use TLS configuration for https (e.g. with own root certificates)
+    pub fn with_tls_config(mut self, config: Arc<ClientConfig>) -> Self {
+        self.config = config;
+        self
+    }
+
     pub fn get(&self, url: impl AsRef<str>) -> Result<HttpResponse> {
         self.request(HttpMethod::Get, url, None)
     }
 
     pub fn post(&self, url: impl AsRef<str>, body: impl AsRef<[u8]>) -> Result<HttpResponse> {
-        self.request(HttpMethod::Get, url, Some(body.as_ref()))
+        self.request(HttpMethod::Post, url, Some(body.as_ref()))
     }
 
     pub fn put(&self, url: impl AsRef<str>, body: impl AsRef<[u8]>) -> Result<HttpResponse> {
@@ -103,7 +204,7 @@ impl HttpClient {
     }
 
     pub fn patch(&self, url: impl AsRef<str>, body: impl AsRef<[u8]>) -> Result<HttpResponse> {
-        self.request(HttpMethod::Put, url, Some(body.as_ref()))
+        self.request(HttpMethod::Patch, url, Some(body.as_ref()))
     }
 
     pub fn head(&self, url: impl AsRef<str>) -> Result<HttpResponse> {
@@ -128,32 +229,227 @@ impl HttpClient {
         url: impl AsRef<str>,
         body: Option<&[u8]>,
     ) -> Result<HttpResponse> {
-        let url = url.as_ref().try_into()?;
-        let mut stream = connect(
+        let body = body.map_or(Body::Empty, Body::Bytes);
+        let options = RequestOptions::new(self.timeouts);
+        self.send(method, url.as_ref(), body, &options)
+    }
+
+    /// Build request with options overriding those of client
+    pub fn request_builder(&self, method: HttpMethod, url: impl ToString) -> RequestBuilder<'_> {
+        RequestBuilder::new(self, method, url.to_string(), self.timeouts)
+    }
+
+    pub(crate) fn send(
+        &self,
+        method: HttpMethod,
+        url: &str,
+        body: Body,
+        options: &RequestOptions,
+    ) -> Result<HttpResponse> {
+        self.stream(method, url, body, options)
+            .and_then(HttpResponse::read)
+            .map_err(TimeoutError::unwrap_io)
+    }
+
+    pub(crate) fn stream(
+        &self,
+        method: HttpMethod,
+        url: &str,
+        body: Body,
+        options: &RequestOptions,
+    ) -> Result<StreamingResponse<'_>> {
+        let deadline = options.timeouts.total.map(|total| Instant::now() + total);
+        self.follow(method, url, body, options, deadline)
+            .map_err(TimeoutError::unwrap_io)
+    }
+
+    fn follow(
+        &self,
+        method: HttpMethod,
+        url: &str,
+        mut body: Body,
+        options: &RequestOptions,
+        deadline: Option<Instant>,
+    ) -> Result<StreamingResponse<'_>> {
+        let max_redirects = match self.redirect_policy {
+            RedirectPolicy::None => 0,
+            RedirectPolicy::Limit(max_redirects) => max_redirects,
+        };
+        let mut method = method;
+        let mut url = Url::parse(url)?;
+        let origin = pool_key(&url);
+        let mut cross_host = false;
+        let mut redirects = Vec::new();
+
+        loop {
+            let (head, connection) =
+                self.request_once(method, &url, &mut body, cross_host, options, deadline)?;
+            if let Some(cookie_jar) = &self.cookie_jar {
+                cookie_jar.store_headers(&url, &head.headers);
+            }
+            let status = head.status;
+            let location = match head
+                .headers
+                .get("location")
+                .and_then(|values| values.first())
+            {
+                Some(location)
+                    if max_redirects > 0 && matches!(status, 301 | 302 | 303 | 307 | 308) =>
+                {
+                    location.clone()
+                }
+                _ => {
+                    return Ok(StreamingResponse::new(
+                        head, connection, redirects, &self.pool,
+                    ));
+                }
+            };
+            if redirects.len() == max_redirects {
+                return Fail::from("too many redirects");
+            }
+
+            // discard body of redirect to reuse connection
+            let mut response = StreamingResponse::new(head, connection, Vec::new(), &self.pool);
+            copy(&mut response, &mut sink())?;
+
+            // resolve location, never send credentials to other hosts
+            url = url.join(&location)?;
+            url.set_fragment(None);
+            cross_host |= pool_key(&url) != origin;
+            redirects.push(url.to_string());
+
+            // 303 and POST with 301/302 continue with GET, 307 and 308 preserve method and body
+            if status == 303 && method != HttpMethod::Head
+                || matches!(status, 301 | 302) && method == HttpMethod::Post
+            {
+                method = HttpMethod::Get;
+                body = Body::Empty;
+            } else if !body.is_repeatable() {
+                return Fail::from("cannot repeat streamed request body for redirect");
+            }
+        }
+    }
+
+    fn request_once(
+        &self,
+        method: HttpMethod,
+        url: &Url,
+        body: &mut Body,
+        cross_host: bool,
+        options: &RequestOptions,
+        deadline: Option<Instant>,
+    ) -> Result<(ResponseHead, PooledConnection)> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Fail::from(format!("unsupported URL scheme: {}", url.scheme()));
+        }
+        let timeouts = options.timeouts;
+        let proxy = self.proxy.select(url);
+
+        // reuse idle connection, retry on new connection if peer closed it meanwhile
+        if let Some(mut connection) = self.pool.take(&connection_key(url, proxy)) {
+            connection.set_timeouts(timeouts, deadline);
+            match self.exchange(connection, method, url, body, cross_host, &options.headers) {
+                Err(err)
+                    if method.is_idempotent()
+                        && body.is_repeatable()
+                        && !is_timeout_error(&err) => {}
+                result => return result,
+            }
+        }
+
+        let mut connection = connect(
             #[cfg(feature = "tls")]
             self.config.clone(),
-            &url,
+            url,
+            proxy,
+            timeouts.connect,
+            deadline,
         )?;
+        connection.set_timeouts(timeouts, deadline);
+        self.exchange(connection, method, url, body, cross_host, &options.headers)
+    }
 
-        send_main_header(&mut stream, method, &url, &self.query)?;
+    fn exchange(
+        &self,
+        mut connection: PooledConnection,
+        method: HttpMethod,
+        url: &Url,
+        body: &mut Body,
+        cross_host: bool,
+        headers: &[(String, String)],
+    ) -> Result<(ResponseHead, PooledConnection)> {
+        // plain requests through proxy in absolute-form, https is tunnelled
+        let proxy = self.proxy.select(url).filter(|_| !url.is_secure());
 
-        if let Some(body) = body {
-            send_content_length(&mut stream, body)?;
-            self.send_headers(&mut stream)?;
-            stream.write_all(body)?;
-        } else {
-            self.send_headers(&mut stream)?;
+        // header written at once, servers count short reads
+        let mut head = Vec::new();
+        send_main_header(&mut head, method, url, &self.query, proxy.is_some())?;
+        send_framing(&mut head, body)?;
+        if let Some(authorization) = proxy.and_then(Proxy::authorization) {
+            send_header(&mut head, "Proxy-Authorization", authorization)?;
         }
+        self.send_headers(&mut head, url, cross_host, headers)?;
+        connection.write_all(&head)?;
+        send_body(&mut connection, body)?;
+        connection.flush()?;
 
-        read_all(&mut stream)
+        let head = read_head(&mut connection, method)?;
+        Ok((head, connection))
     }
 
-    fn send_headers(&self, stream: &mut impl Write) -> Result<()> {
-        for (name, values) in &self.headers {
-            for value in values {
+    fn send_headers(
+        &self,
+        stream: &mut impl Write,
+        url: &Url,
+        cross_host: bool,
+        headers: &[(String, String)],
+    ) -> Result<()> {
+        // headers of request replace those of client
+        let has_header = |name: &str| {
+            self.headers
+                .keys()
+                .chain(headers.iter().map(|(name, _)| name))
+                .any(|header| header.eq_ignore_ascii_case(name))
+        };
+        let client_headers = self
+            .headers
+            .iter()
+            .filter(|(name, _)| {
+                !headers
+                    .iter()
+                    .any(|(header, _)| header.eq_ignore_ascii_case(name))
+            })
+            .flat_map(|(name, values)| values.iter().map(move |value| (name, value)));
+        let request_headers = headers.iter().map(|(name, value)| (name, value));
+        for (name, value) in client_headers.chain(request_headers) {
+            if !(cross_host && name.eq_ignore_ascii_case("authorization")) {
                 send_header(stream, name, value)?;
             }
         }
+
+        // cookies of jar unless set explicitly
+        if let Some(cookie_jar) = &self.cookie_jar
+            && !has_header("cookie")
+            && let Some(cookie) = cookie_jar.header_url(url)
+        {
+            send_header(stream, "Cookie", &cookie)?;
+        }
+
+        // propagate id of request handled by this thread
+        if let Some(request_id) = current_request_id()
+            && !has_header("x-request-id")
+        {
+            send_header(stream, "X-Request-Id", &request_id)?;
+        }
+
+        // ask to keep connection open if pooling
+        if !has_header("connection") {
+            let connection = match self.pool.max_idle() {
+                0 => "close",
+                _ => "keep-alive",
+            };
+            send_header(stream, "Connection", connection)?;
+        }
         end_headers(stream)
     }
 }
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::fs::{read_to_string, write};
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::http::date::parse_cookie_date;
+use crate::http::url::Url;
+use crate::{Fail, Result};
+
+/// Longest accepted Max-Age
+const MAX_AGE_LIMIT: u64 = u32::MAX as u64;
+
+/// Cookie stored in CookieJar
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Lowercase domain without leading dot
+    pub domain: String,
+    pub path: String,
+    /// Expiry time, None for session cookies
+    pub expires: Option<SystemTime>,
+    /// Only sent to domain itself, not to subdomains
+    pub host_only: bool,
+    /// Only sent over HTTPS
+    pub secure: bool,
+    pub http_only: bool,
+}
+
+impl Cookie {
+    /// Parse Set-Cookie header received from url (RFC 6265 section 5.2 and 5.3)
+    fn parse(set_cookie: &str, url: &Url) -> Option<Self> {
+        let host = url.hostname();
+        let (pair, attributes) = set_cookie.split_once(';').unwrap_or((set_cookie, ""));
+        let (name, value) = pair.split_once('=')?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty() {
+            return None;
+        }
+
+        // attributes, last one wins
+        let mut cookie = Self {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: host.to_string(),
+            path: default_path(url.path()),
+            expires: None,
+            host_only: true,
+            secure: false,
+            http_only: false,
+        };
+        let (mut max_age, mut expires, mut domain) = (None, None, None);
+        for attribute in attributes.split(';') {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let (key, value) = (key.trim().to_lowercase(), value.trim());
+            match key.as_str() {
+                "expires" => expires = parse_cookie_date(value).ok().or(expires),
+                "max-age" => max_age = parse_max_age(value).or(max_age),
+                "domain" if !value.is_empty() => {
+                    domain = Some(value.trim_start_matches('.').to_lowercase())
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "path" => cookie.path = default_path(url.path()),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                _ => {}
+            }
+        }
+        cookie.expires = max_age.or(expires);
+
+        // domain must cover host, single labels only for host itself (no public suffix list)
+        if let Some(domain) = domain {
+            if !domain_match(host, &domain) || !domain.contains('.') && domain != host {
+                return None;
+            }
+            if domain != host {
+                cookie.domain = domain;
+                cookie.host_only = false;
+            }
+        }
+
+        // secure cookies only from secure origins
+        if cookie.secure && !url.is_secure() {
+            return None;
+        }
+        Some(cookie)
+    }
+
+    /// Whether cookie expired
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Whether cookie is sent with request to url (RFC 6265 section 5.4)
+    fn matches(&self, url: &Url) -> bool {
+        let host = url.hostname();
+        let domain = match self.host_only {
+            true => host == self.domain,
+            false => domain_match(host, &self.domain),
+        };
+        domain && path_match(url.path(), &self.path) && (url.is_secure() || !self.secure)
+    }
+}
+
+/// Cookies received by HttpClient, shareable between clients
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    cookies: Mutex<Vec<Cookie>>,
+}
+
+impl CookieJar {
+    /// Create new empty CookieJar
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load persistent cookies from Netscape cookie file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let now = SystemTime::now();
+        let mut cookies = Vec::new();
+        for line in read_to_string(path)?.lines() {
+            let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+                Some(line) => (line, true),
+                None => (line, false),
+            };
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // domain, subdomains, path, secure, expires, name, value
+            let [domain, subdomains, path, secure, expires, name, value] =
+                line.splitn(7, '\t').collect::<Vec<&str>>()[..]
+            else {
+                return Fail::from("invalid line in cookie file");
+            };
+            let cookie = Cookie {
+                name: name.to_string(),
+                value: value.to_string(),
+                domain: domain.trim_start_matches('.').to_lowercase(),
+                path: path.to_string(),
+                expires: match expires.parse()? {
+                    0 => None,
+                    secs => Some(UNIX_EPOCH + Duration::from_secs(secs)),
+                },
+                host_only: subdomains != "TRUE",
+                secure: secure == "TRUE",
+                http_only,
+            };
+            if !cookie.is_expired(now) {
+                cookies.push(cookie);
+            }
+        }
+        Ok(Self {
+            cookies: Mutex::new(cookies),
+        })
+    }
+
+    /// Save persistent cookies to Netscape cookie file, session cookies are dropped
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = String::from("# Netscape HTTP Cookie File\n");
+        for cookie in self.cookies() {
+            let Some(expires) = cookie.expires else {
+                continue;
+            };
+            let expires = expires
+                .duration_since(UNIX_EPOCH)
+                .map(|expires| expires.as_secs().max(1))
+                .unwrap_or(1);
+            file.push_str(&format!(
+                "{}{}{}\t{}\t{}\t{}\t{expires}\t{}\t{}\n",
+                if cookie.http_only { "#HttpOnly_" } else { "" },
+                if cookie.host_only { "" } else { "." },
+                cookie.domain,
+                if cookie.host_only { "FALSE" } else { "TRUE" },
+                cookie.path,
+                if cookie.secure { "TRUE" } else { "FALSE" },
+                cookie.name,
+                cookie.value
+            ));
+        }
+        Ok(write(path, file)?)
+    }
+
+    /// Store cookie of Set-Cookie header received from url
+    pub fn store(&self, url: &str, set_cookie: &str) -> Result<()> {
+        self.store_url(&Url::parse(url)?, [set_cookie]);
+        Ok(())
+    }
+
+    /// Get Cookie header for request to url, None if no cookie matches
+    pub fn header(&self, url: &str) -> Result<Option<String>> {
+        Ok(self.header_url(&Url::parse(url)?))
+    }
+
+    /// Get all unexpired cookies
+    pub fn cookies(&self) -> Vec<Cookie> {
+        let now = SystemTime::now();
+        self.cookies
+            .lock()
+            .map(|cookies| {
+                cookies
+                    .iter()
+                    .filter(|cookie| !cookie.is_expired(now))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Remove all cookies
+    pub fn clear(&self) {
+        if let Ok(mut cookies) = self.cookies.lock() {
+            cookies.clear();
+        }
+    }
+
+    /// Store cookies of response headers
+    pub(crate) fn store_headers(&self, url: &Url, headers: &HashMap<String, Vec<String>>) {
+        if let Some(values) = headers.get("set-cookie") {
+            self.store_url(url, values.iter().map(String::as_str));
+        }
+    }
+
+    /// Store cookies, replacing those of same name, domain and path
+    fn store_url<'s>(&self, url: &Url, set_cookies: impl IntoIterator<Item = &'s str>) {
+        let Ok(mut cookies) = self.cookies.lock() else {
+            return;
+        };
+        let now = SystemTime::now();
+        for cookie in set_cookies
+            .into_iter()
+            .filter_map(|set_cookie| Cookie::parse(set_cookie, url))
+        {
+            let existing = cookies.iter().position(|stored| {
+                stored.name == cookie.name
+                    && stored.domain == cookie.domain
+                    && stored.path == cookie.path
+            });
+            match existing {
+                // expired cookies delete stored ones
+                Some(index) if cookie.is_expired(now) => {
+                    cookies.remove(index);
+                }
+                Some(index) => cookies[index] = cookie,
+                None if !cookie.is_expired(now) => cookies.push(cookie),
+                None => {}
+            }
+        }
+        cookies.retain(|cookie| !cookie.is_expired(now));
+    }
+
+    /// Get Cookie header of matching cookies, longer paths first
+    pub(crate) fn header_url(&self, url: &Url) -> Option<String> {
+        let now = SystemTime::now();
+        let cookies = self.cookies.lock().ok()?;
+        let mut matching: Vec<&Cookie> = cookies
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now) && cookie.matches(url))
+            .collect();
+        if matching.is_empty() {
+            return None;
+        }
+        matching.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        let pairs: Vec<String> = matching
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect();
+        Some(pairs.join("; "))
+    }
+}
+
+/// Parse Max-Age, non-positive values expire immediately
+fn parse_max_age(value: &str) -> Option<SystemTime> {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    match value.parse::<i64>() {
+        Ok(..=0) => Some(UNIX_EPOCH),
+        Ok(secs) => Some(SystemTime::now() + Duration::from_secs((secs as u64).min(MAX_AGE_LIMIT))),
+        Err(_) if value.starts_with('-') => Some(UNIX_EPOCH),
+        Err(_) => Some(SystemTime::now() + Duration::from_secs(MAX_AGE_LIMIT)),
+    }
+}
+
+/// Directory of request path, "/" if none (RFC 6265 section 5.1.4)
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => path[..index].to_string(),
+    }
+}
+
+/// Whether host is domain or a subdomain of it, IP addresses only match exactly
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+            && host.parse::<IpAddr>().is_err()
+}
+
+/// Whether request path is cookie path or below it
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || path
+            .strip_prefix(cookie_path)
+            .is_some_and(|rest| cookie_path.ends_with('/') || rest.starts_with('/'))
+}
@@ -1,11 +1,22 @@
 //! HTTP client
 //! Work in progress
 
+mod builder;
 #[allow(clippy::module_inception)]
 mod client;
+mod cookie;
+mod multipart;
+mod pool;
+mod proxy;
 mod request;
 mod response;
-mod url;
+mod timeout;
 
+pub use builder::*;
 pub use client::*;
-pub use response::HttpResponse;
+pub use cookie::{Cookie, CookieJar};
+pub use multipart::MultipartForm;
+pub use pool::ConnectionPool;
+pub use proxy::{Proxy, ProxyConfig};
+pub use response::{HttpResponse, StreamingResponse};
+pub use timeout::*;
@@ -0,0 +1,204 @@
+use std::collections::VecDeque;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::fs::File;
+use std::io::{Cursor, Read, Result as IoResult};
+use std::path::Path;
+
+use crate::Result;
+use crate::http::common::generate_id;
+
+/// Builder of multipart/form-data request bodies, serialized while sending
+pub struct MultipartForm<'a> {
+    boundary: String,
+    parts: Vec<Part<'a>>,
+}
+
+/// Part of form with serialized headers
+struct Part<'a> {
+    header: Vec<u8>,
+    content: Content<'a>,
+}
+
+/// Content of part
+enum Content<'a> {
+    Bytes(Vec<u8>),
+    Reader(Box<dyn Read + 'a>, Option<u64>),
+}
+
+impl<'a> MultipartForm<'a> {
+    /// Create new empty MultipartForm with random boundary
+    pub fn new() -> Self {
+        Self {
+            boundary: format!("----kern{}", generate_id()),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Get boundary between parts
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// Get value of Content-Type header
+    pub fn content_type(&self) -> String {
+        format!("multipart/form-data; boundary={}", self.boundary)
+    }
+
+    /// Add text field
+    pub fn text(mut self, name: &str, value: impl Into<String>) -> Self {
+        let header = self.part_header(name, None, None);
+        let content = Content::Bytes(value.into().into_bytes());
+        self.parts.push(Part { header, content });
+        self
+    }
+
+    /// Add file part from bytes
+    pub fn bytes(
+        mut self,
+        name: &str,
+        filename: &str,
+        content_type: &str,
+        data: impl Into<Vec<u8>>,
+    ) -> Self {
+        let header = self.part_header(name, Some(filename), Some(content_type));
+        let content = Content::Bytes(data.into());
+        self.parts.push(Part { header, content });
+        self
+    }
+
+    /// Add file part streamed from reader, form length is unknown if length is None
+    pub fn reader(
+        mut self,
+        name: &str,
+        filename: &str,
+        content_type: &str,
+        reader: impl Read + 'a,
+        length: Option<u64>,
+    ) -> Self {
+        let header = self.part_header(name, Some(filename), Some(content_type));
+        let content = Content::Reader(Box::new(reader), length);
+        self.parts.push(Part { header, content });
+        self
+    }
+
+    /// Add file part streamed from file at path as application/octet-stream
+    pub fn file(self, name: &str, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let length = file.metadata()?.len();
+        let filename = path
+            .file_name()
+            .map(|filename| filename.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(self.reader(
+            name,
+            &filename,
+            "application/octet-stream",
+            file,
+            Some(length),
+        ))
+    }
+
+    /// Get length of serialized form, None if length of a reader is unknown
+    pub fn length(&self) -> Option<u64> {
+        let mut length = self.closing().len() as u64;
+        for part in &self.parts {
+            let content = match &part.content {
+                Content::Bytes(bytes) => bytes.len() as u64,
+                Content::Reader(_, length) => (*length)?,
+            };
+            length += part.header.len() as u64 + content + 2;
+        }
+        Some(length)
+    }
+
+    /// Reader of serialized form
+    pub fn into_reader(self) -> impl Read + 'a {
+        let closing = self.closing();
+        let mut segments: VecDeque<Box<dyn Read + 'a>> = VecDeque::new();
+        for part in self.parts {
+            segments.push_back(Box::new(Cursor::new(part.header)));
+            match part.content {
+                Content::Bytes(bytes) => segments.push_back(Box::new(Cursor::new(bytes))),
+                Content::Reader(reader, Some(length)) => {
+                    segments.push_back(Box::new(reader.take(length)))
+                }
+                Content::Reader(reader, None) => segments.push_back(reader),
+            }
+            segments.push_back(Box::new(&b"\r\n"[..]));
+        }
+        segments.push_back(Box::new(Cursor::new(closing)));
+        Segments(segments)
+    }
+
+    /// Serialize delimiter and headers of part
+    fn part_header(
+        &self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: Option<&str>,
+    ) -> Vec<u8> {
+        let mut header = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            self.boundary,
+            escape(name)
+        );
+        if let Some(filename) = filename {
+            header.push_str(&format!("; filename=\"{}\"", escape(filename)));
+        }
+        header.push_str("\r\n");
+        if let Some(content_type) = content_type {
+            // line breaks would start new headers or parts
+            let content_type = content_type.replace(['\r', '\n'], "");
+            header.push_str(&format!("Content-Type: {content_type}\r\n"));
+        }
+        header.push_str("\r\n");
+        header.into_bytes()
+    }
+
+    /// Serialize closing delimiter
+    fn closing(&self) -> Vec<u8> {
+        format!("--{}--\r\n", self.boundary).into_bytes()
+    }
+}
+
+impl Default for MultipartForm<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for MultipartForm<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("MultipartForm")
+            .field("boundary", &self.boundary)
+            .field("parts", &self.parts.len())
+            .field("length", &self.length())
+            .finish()
+    }
+}
+
+/// Escape quotes and line breaks in names and filenames
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Readers read one after another
+struct Segments<'a>(VecDeque<Box<dyn Read + 'a>>);
+
+impl Read for Segments<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        while let Some(segment) = self.0.front_mut() {
+            match segment.read(buf)? {
+                0 if !buf.is_empty() => {
+                    self.0.pop_front();
+                }
+                read => return Ok(read),
+            }
+        }
+        Ok(0)
+    }
+}
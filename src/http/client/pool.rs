@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::io::{BufRead, BufReader, ErrorKind, Read, Result as IoResult, Write};
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::http::common::ReadWrite;
+
+use super::timeout::{TimeoutError, TimeoutKind, Timeouts, is_timeout, limit};
+
+/// Stream of client connection (plain TCP or TLS)
+pub(crate) type ClientStream = Box<dyn ReadWrite + Send>;
+
+/// Connection to a host, buffered for reading responses
+pub(crate) struct PooledConnection {
+    key: String,
+    reader: BufReader<ClientStream>,
+    socket: TcpStream,
+    timeouts: Timeouts,
+    deadline: Option<Instant>,
+}
+
+impl PooledConnection {
+    /// Create new PooledConnection of stream and its underlying socket
+    pub fn new(key: String, stream: ClientStream, socket: TcpStream) -> Self {
+        Self {
+            key,
+            reader: BufReader::new(stream),
+            socket,
+            timeouts: Timeouts::new(),
+            deadline: None,
+        }
+    }
+
+    /// Set read and write timeouts and deadline of request
+    pub fn set_timeouts(&mut self, timeouts: Timeouts, deadline: Option<Instant>) {
+        self.timeouts = timeouts;
+        self.deadline = deadline;
+    }
+
+    /// Set socket read timeout before reading into empty buffer
+    fn before_read(&self) -> IoResult<()> {
+        if self.reader.buffer().is_empty() {
+            let timeout = limit(self.timeouts.read, self.deadline)?;
+            self.socket.set_read_timeout(timeout)?;
+        }
+        Ok(())
+    }
+
+    /// Set socket write timeout before writing
+    fn before_write(&self) -> IoResult<()> {
+        let timeout = limit(self.timeouts.write, self.deadline)?;
+        self.socket.set_write_timeout(timeout)
+    }
+
+    /// Replace timeout errors with TimeoutError
+    fn timeout_error(&self, err: std::io::Error, kind: TimeoutKind) -> std::io::Error {
+        if !is_timeout(&err) {
+            return err;
+        }
+        match limit(None, self.deadline) {
+            Ok(_) => TimeoutError::io(kind),
+            Err(total) => total,
+        }
+    }
+
+    /// Check whether peer closed the connection or sent unexpected response data<br>
+    /// Pending socket data is no sign of a dead connection, TLS peers send records like session tickets
+    fn is_alive(&self) -> bool {
+        if !self.reader.buffer().is_empty() || self.socket.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let alive = match self.socket.peek(&mut [0u8]) {
+            Ok(length) => length > 0,
+            Err(err) => err.kind() == ErrorKind::WouldBlock,
+        };
+        self.socket.set_nonblocking(false).is_ok() && alive
+    }
+}
+
+impl Read for PooledConnection {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        self.before_read()?;
+        self.reader
+            .read(buf)
+            .map_err(|err| self.timeout_error(err, TimeoutKind::Read))
+    }
+}
+
+impl BufRead for PooledConnection {
+    fn fill_buf(&mut self) -> IoResult<&[u8]> {
+        self.before_read()?;
+        if let Err(err) = self.reader.fill_buf() {
+            return Err(self.timeout_error(err, TimeoutKind::Read));
+        }
+        Ok(self.reader.buffer())
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.reader.consume(amount)
+    }
+}
+
+impl Write for PooledConnection {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.before_write()?;
+        self.reader
+            .get_mut()
+            .write(buf)
+            .map_err(|err| self.timeout_error(err, TimeoutKind::Write))
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.before_write()?;
+        self.reader
+            .get_mut()
+            .flush()
+            .map_err(|err| self.timeout_error(err, TimeoutKind::Write))
+    }
+}
+
+/// Idle keep-alive connections by host
+pub struct ConnectionPool {
+    idle: Mutex<HashMap<String, Vec<(PooledConnection, Instant)>>>,
+    max_idle: usize,
+    idle_timeout: Duration,
+}
+
+impl ConnectionPool {
+    /// Create new ConnectionPool keeping at most max_idle connections per host for idle_timeout
+    pub fn new(max_idle: usize, idle_timeout: Duration) -> Self {
+        Self {
+            idle: Mutex::new(HashMap::new()),
+            max_idle,
+            idle_timeout,
+        }
+    }
+
+    /// Get maximum number of idle connections per host (0 disables pooling)
+    pub fn max_idle(&self) -> usize {
+        self.max_idle
+    }
+
+    /// Get how long connections may stay idle
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    /// Get number of idle connections of all hosts
+    pub fn idle(&self) -> usize {
+        self.idle
+            .lock()
+            .map(|idle| idle.values().map(Vec::len).sum())
+            .unwrap_or_default()
+    }
+
+    /// Take most recently used live connection to host
+    pub(crate) fn take(&self, key: &str) -> Option<PooledConnection> {
+        let mut idle = self.idle.lock().ok()?;
+        let connections = idle.get_mut(key)?;
+
+        // discard expired and closed connections
+        while let Some((connection, since)) = connections.pop() {
+            if since.elapsed() < self.idle_timeout && connection.is_alive() {
+                return Some(connection);
+            }
+        }
+        idle.remove(key);
+        None
+    }
+
+    /// Return connection after complete response
+    pub(crate) fn put(&self, connection: PooledConnection) {
+        let Ok(mut idle) = self.idle.lock() else {
+            return;
+        };
+        let connections = idle.entry(connection.key.clone()).or_default();
+        connections.retain(|(_, since)| since.elapsed() < self.idle_timeout);
+        if connections.len() < self.max_idle {
+            connections.push((connection, Instant::now()));
+        }
+    }
+
+    /// Close all idle connections
+    pub fn clear(&self) {
+        if let Ok(mut idle) = self.idle.lock() {
+            idle.clear();
+        }
+    }
+}
+
+impl Debug for ConnectionPool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("ConnectionPool")
+            .field("idle", &self.idle())
+            .field("max_idle", &self.max_idle)
+            .field("idle_timeout", &self.idle_timeout)
+            .finish()
+    }
+}
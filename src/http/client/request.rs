@@ -1,12 +1,17 @@
 use core::str;
 use std::collections::HashMap;
-use std::io::Write;
-use std::net::TcpStream;
+use std::io::{BufReader, ErrorKind, Read, Write, copy};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
 
-use crate::http::common::{HttpMethod, ReadWrite};
+use crate::http::common::HttpMethod;
+use crate::http::url::Url;
 use crate::{Fail, Result};
 
-use super::url::{Url, url_encode};
+use super::pool::{ClientStream, PooledConnection};
+use super::proxy::Proxy;
+use super::response::read_head;
+use super::timeout::{TimeoutError, TimeoutKind, is_timeout, is_timeout_error, limit};
 
 #[cfg(feature = "tls")]
 use {
@@ -14,8 +19,62 @@ use {
     std::sync::Arc,
 };
 
-pub fn send_content_length(stream: &mut impl Write, body: &[u8]) -> Result<()> {
-    send_header(stream, "Content-Length", &body.len().to_string()).or_else(Fail::from)
+/// Size of chunks of bodies with unknown length
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// Body of request, readers can be sent only once
+pub enum Body<'r> {
+    Empty,
+    Bytes(&'r [u8]),
+    Reader(&'r mut dyn Read, Option<u64>),
+}
+
+impl Body<'_> {
+    pub fn is_repeatable(&self) -> bool {
+        !matches!(self, Body::Reader(..))
+    }
+}
+
+/// Send Content-Length or Transfer-Encoding header
+pub fn send_framing(stream: &mut impl Write, body: &Body) -> Result<()> {
+    match body {
+        Body::Empty => Ok(()),
+        Body::Bytes(bytes) => send_header(stream, "Content-Length", &bytes.len().to_string()),
+        Body::Reader(_, Some(length)) => send_header(stream, "Content-Length", &length.to_string()),
+        Body::Reader(_, None) => send_header(stream, "Transfer-Encoding", "chunked"),
+    }
+}
+
+/// Send body after headers, readers of unknown length chunked
+pub fn send_body(stream: &mut impl Write, body: &mut Body) -> Result<()> {
+    match body {
+        Body::Empty => {}
+        Body::Bytes(bytes) => stream.write_all(bytes)?,
+        Body::Reader(reader, Some(length)) => {
+            if copy(&mut reader.take(*length), stream)? != *length {
+                return Fail::from("request body shorter than its length");
+            }
+        }
+        Body::Reader(reader, None) => {
+            let mut buf = vec![0u8; CHUNK_SIZE];
+            loop {
+                let length = match reader.read(&mut buf) {
+                    Ok(length) => length,
+                    Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err.into()),
+                };
+                // last chunk of size 0 ends body without trailers
+                let mut chunk = format!("{length:x}\r\n").into_bytes();
+                chunk.extend_from_slice(&buf[..length]);
+                chunk.extend_from_slice(b"\r\n");
+                stream.write_all(&chunk)?;
+                if length == 0 {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
 }
 
 pub fn end_headers(stream: &mut impl Write) -> Result<()> {
@@ -27,20 +86,24 @@ pub fn send_main_header(
     method: HttpMethod,
     url: &Url,
     query: &HashMap<String, String>,
+    absolute: bool,
 ) -> Result<()> {
+    let host = url.host_port().unwrap_or_default();
+    let mut target = url.clone();
+    for (name, value) in query {
+        target.append_query_pair(name, value);
+    }
+
     stream.write_all(method.as_str().as_bytes())?;
-    stream.write_all(" /".as_bytes())?;
-    stream.write_all(url_encode(url.path, true).as_bytes())?;
-
-    for (i, (name, value)) in query.iter().enumerate() {
-        stream.write_all(&[if i == 0 { b'?' } else { b'&' }])?;
-        stream.write_all(url_encode(name, false).as_bytes())?;
-        stream.write_all(b"=")?;
-        stream.write_all(url_encode(value, false).as_bytes())?;
+    stream.write_all(b" ")?;
+    // absolute-form for forward proxies
+    if absolute {
+        stream.write_all(format!("{}://{host}", url.scheme()).as_bytes())?;
     }
+    stream.write_all(target.request_target().as_bytes())?;
 
     stream.write_all(" HTTP/1.1\r\nHost: ".as_bytes())?;
-    stream.write_all(url.server_name.as_bytes())?;
+    stream.write_all(host.as_bytes())?;
     stream.write_all("\r\n".as_bytes())?;
     Ok(())
 }
@@ -55,22 +118,115 @@ pub fn send_header(stream: &mut impl Write, name: &str, value: &str) -> Result<(
 pub fn connect(
     #[cfg(feature = "tls")] config: Arc<ClientConfig>,
     url: &Url,
-) -> Result<Box<dyn ReadWrite>> {
-    let stream = TcpStream::connect(&url.addr)?;
+    proxy: Option<&Proxy>,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+) -> Result<PooledConnection> {
+    let addr = proxy.map_or_else(|| url.socket_addr(), |proxy| proxy.addr().to_string());
+    let socket = match limit(timeout, deadline)? {
+        Some(timeout) => connect_timeout(&addr, timeout, deadline)?,
+        None => TcpStream::connect(&addr)?,
+    };
+
+    // tunnel https through proxy, timeouts of tunnel count as connect timeout
+    if url.is_secure()
+        && let Some(proxy) = proxy
+    {
+        tunnel(&socket, url, proxy, timeout, deadline).map_err(|err| {
+            match is_timeout_error(&err) {
+                true => limit(None, deadline)
+                    .err()
+                    .unwrap_or_else(|| TimeoutError::io(TimeoutKind::Connect))
+                    .into(),
+                false => err,
+            }
+        })?;
+    }
 
-    let stream: Box<dyn ReadWrite> = if url.secure {
+    let stream: ClientStream = if url.is_secure() {
         #[cfg(not(feature = "tls"))]
         return Fail::from("tls feature not enabled");
 
         #[cfg(feature = "tls")]
         {
-            let server_name = url.server_name.to_string().try_into()?;
+            let server_name = url.hostname().to_string().try_into()?;
             let conn = ClientConnection::new(config, server_name).or_else(Fail::from)?;
-            Box::new(StreamOwned::new(conn, stream))
+            Box::new(StreamOwned::new(conn, socket.try_clone()?))
         }
     } else {
-        Box::new(stream)
+        Box::new(socket.try_clone()?)
     };
 
-    Ok(stream)
+    Ok(PooledConnection::new(
+        connection_key(url, proxy),
+        stream,
+        socket,
+    ))
+}
+
+/// Open tunnel to host of url through proxy with CONNECT
+fn tunnel(
+    socket: &TcpStream,
+    url: &Url,
+    proxy: &Proxy,
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+) -> Result<()> {
+    let timeout = limit(timeout, deadline)?;
+    socket.set_read_timeout(timeout)?;
+    socket.set_write_timeout(timeout)?;
+
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", url.socket_addr());
+    if let Some(authorization) = proxy.authorization() {
+        request.push_str(&format!("Proxy-Authorization: {authorization}\r\n"));
+    }
+    request.push_str("\r\n");
+    let mut stream = socket;
+    stream.write_all(request.as_bytes())?;
+
+    // proxy must not send data before tunnel is established
+    let mut reader = BufReader::new(socket);
+    let head = read_head(&mut reader, HttpMethod::Connect)?;
+    if !(200..300).contains(&head.status) {
+        return Fail::from(format!("proxy refused tunnel with status {}", head.status));
+    }
+    if !reader.buffer().is_empty() {
+        return Fail::from("proxy sent data before tunnel was established");
+    }
+    socket.set_read_timeout(None)?;
+    socket.set_write_timeout(None)?;
+    Ok(())
+}
+
+fn connect_timeout(addr: &str, timeout: Duration, deadline: Option<Instant>) -> Result<TcpStream> {
+    let mut last_err = None;
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) if is_timeout(&err) => {
+                last_err = Some(match limit(None, deadline) {
+                    Ok(_) => TimeoutError::io(TimeoutKind::Connect),
+                    Err(total) => total,
+                })
+            }
+            Err(err) => last_err = Some(err),
+        }
+    }
+    match last_err {
+        Some(err) => Err(err.into()),
+        None => Fail::from("could not resolve address"),
+    }
+}
+
+/// Key of pooled connections, plain connections to a proxy are shared by all hosts
+pub fn connection_key(url: &Url, proxy: Option<&Proxy>) -> String {
+    match proxy {
+        Some(proxy) if url.is_secure() => format!("{} via {}", pool_key(url), proxy.addr()),
+        Some(proxy) => format!("proxy://{}", proxy.addr()),
+        None => pool_key(url),
+    }
+}
+
+pub fn pool_key(url: &Url) -> String {
+    format!("{}://{}", url.scheme(), url.socket_addr())
 }
@@ -1,14 +1,26 @@
 use core::str;
 use std::collections::HashMap;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, Error as IoError, ErrorKind, Read, Result as IoResult, Write};
 
+use crate::http::common::HttpMethod;
 use crate::{Fail, Result};
 
+use super::pool::{ConnectionPool, PooledConnection};
+use super::timeout::TimeoutError;
+
+/// Maximum size of status line and headers, or of trailers
+const MAX_HEADER_SIZE: usize = 64 * 1024;
+
+/// Maximum length of chunk size line including extensions
+const MAX_CHUNK_LINE: usize = 4096;
+
 #[derive(Clone, Debug)]
 pub struct HttpResponse {
     headers: HashMap<String, Vec<String>>,
     status: u16,
     body: Vec<u8>,
+    trailers: HashMap<String, Vec<String>>,
+    redirects: Vec<String>,
 }
 
 impl HttpResponse {
@@ -33,67 +45,350 @@ impl HttpResponse {
     pub fn body_text(&self) -> Result<&str> {
         str::from_utf8(&self.body).or_else(Fail::from)
     }
+
+    pub fn trailers(&self) -> &HashMap<String, Vec<String>> {
+        &self.trailers
+    }
+
+    /// URLs of followed redirects in order (last is URL of this response)
+    pub fn redirects(&self) -> &[String] {
+        &self.redirects
+    }
+
+    /// Read whole body of streaming response
+    pub(crate) fn read(mut response: StreamingResponse) -> Result<Self> {
+        let mut body = Vec::new();
+        response.read_to_end(&mut body)?;
+        Ok(Self {
+            headers: response.headers,
+            status: response.status,
+            body,
+            trailers: response.body.trailers,
+            redirects: response.redirects,
+        })
+    }
 }
 
-pub fn read_all(stream: &mut impl Read) -> Result<HttpResponse> {
-    let mut reader = BufReader::new(stream);
-    let (headers, status) = read_headers(&mut reader)?;
+/// Response with body read on demand, connection is reused once body was read completely
+pub struct StreamingResponse<'a> {
+    headers: HashMap<String, Vec<String>>,
+    status: u16,
+    body: BodyReader<PooledConnection>,
+    redirects: Vec<String>,
+    pool: &'a ConnectionPool,
+}
 
-    let content_length: Option<usize> = headers
-        .get("content-length")
-        .and_then(|values| values.first())
-        .and_then(|content_length| content_length.parse().ok());
-    let mut body;
-    if let Some(content_length) = content_length {
-        body = vec![0u8; content_length];
-        reader.read_exact(&mut body)?;
-    } else {
-        let mut buf = String::new();
-        while reader.read_line(&mut buf)? != 2 {}
-        body = buf.as_bytes().to_owned();
+impl<'a> StreamingResponse<'a> {
+    pub(crate) fn new(
+        head: ResponseHead,
+        connection: PooledConnection,
+        redirects: Vec<String>,
+        pool: &'a ConnectionPool,
+    ) -> Self {
+        let mut response = Self {
+            headers: head.headers,
+            status: head.status,
+            body: BodyReader::new(connection, head.framing, head.keep_alive),
+            redirects,
+            pool,
+        };
+        response.release();
+        response
     }
 
-    Ok(HttpResponse {
-        headers,
+    /// Return connection to pool after complete body
+    fn release(&mut self) {
+        if self.body.reusable()
+            && let Some(connection) = self.body.reader.take()
+        {
+            self.pool.put(connection);
+        }
+    }
+
+    pub fn headers(&self) -> &HashMap<String, Vec<String>> {
+        &self.headers
+    }
+
+    pub fn header_first(&self, name: impl AsRef<str>) -> Option<&str> {
+        self.headers
+            .get(name.as_ref())
+            .and_then(|values| values.first().map(|first| first.as_str()))
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Trailers, available after body was read completely
+    pub fn trailers(&self) -> &HashMap<String, Vec<String>> {
+        &self.body.trailers
+    }
+
+    /// URLs of followed redirects in order (last is URL of this response)
+    pub fn redirects(&self) -> &[String] {
+        &self.redirects
+    }
+
+    /// Copy rest of body to writer, returns number of copied bytes
+    pub fn copy_to(&mut self, writer: &mut impl Write) -> Result<u64> {
+        std::io::copy(self, writer).map_err(|err| TimeoutError::unwrap_io(err.into()))
+    }
+}
+
+impl Read for StreamingResponse<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let length = self.body.read(buf)?;
+        self.release();
+        Ok(length)
+    }
+}
+
+impl std::fmt::Debug for StreamingResponse<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamingResponse")
+            .field("headers", &self.headers)
+            .field("status", &self.status)
+            .field("redirects", &self.redirects)
+            .finish()
+    }
+}
+
+/// How the body of a response is delimited (RFC 9112 6.3)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Framing {
+    Empty,
+    Length(usize),
+    Chunked,
+    Close,
+}
+
+/// Status line and headers of response
+pub(crate) struct ResponseHead {
+    pub status: u16,
+    pub headers: HashMap<String, Vec<String>>,
+    pub framing: Framing,
+    pub keep_alive: bool,
+}
+
+/// Reads body according to framing
+pub(crate) struct BodyReader<R> {
+    reader: Option<R>,
+    framing: Framing,
+    keep_alive: bool,
+    remaining: usize,
+    done: bool,
+    trailers: HashMap<String, Vec<String>>,
+}
+
+impl<R> BodyReader<R> {
+    pub fn new(reader: R, framing: Framing, keep_alive: bool) -> Self {
+        Self {
+            reader: Some(reader),
+            framing,
+            keep_alive,
+            remaining: match framing {
+                Framing::Length(length) => length,
+                _ => 0,
+            },
+            done: matches!(framing, Framing::Empty | Framing::Length(0)),
+            trailers: HashMap::new(),
+        }
+    }
+
+    /// Whether body was read completely and connection can be reused
+    pub fn reusable(&self) -> bool {
+        self.done && self.keep_alive && self.framing != Framing::Close
+    }
+}
+
+impl<R: BufRead> Read for BodyReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        let Some(reader) = self.reader.as_mut() else {
+            return Ok(0);
+        };
+        if self.done || buf.is_empty() {
+            return Ok(0);
+        }
+
+        let length = match self.framing {
+            Framing::Empty => 0,
+            Framing::Length(_) => read_limited(reader, &mut self.remaining, buf)?,
+            Framing::Chunked => {
+                if self.remaining == 0 {
+                    // chunk size with optional extensions
+                    let mut line = String::new();
+                    if reader.take(MAX_CHUNK_LINE as u64).read_line(&mut line)? == 0 {
+                        return Err(ErrorKind::UnexpectedEof.into());
+                    } else if !line.ends_with('\n') {
+                        return Err(invalid_data("chunk size line too long"));
+                    }
+                    let size = line.split(';').next().unwrap_or_default().trim();
+                    if size.is_empty() || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+                        return Err(invalid_data("invalid chunk size"));
+                    }
+                    self.remaining = usize::from_str_radix(size, 16)
+                        .map_err(|_| invalid_data("invalid chunk size"))?;
+
+                    // trailer fields until empty line
+                    if self.remaining == 0 {
+                        let mut raw_trailers = String::new();
+                        read_block(reader, &mut raw_trailers)?;
+                        self.trailers = parse_fields(&raw_trailers)
+                            .map_err(|err| invalid_data(&err.to_string()))?;
+                        self.done = true;
+                        return Ok(0);
+                    }
+                }
+
+                // chunk data followed by CRLF
+                let length = read_limited(reader, &mut self.remaining, buf)?;
+                if self.remaining == 0 {
+                    let mut crlf = [0u8; 2];
+                    reader.read_exact(&mut crlf)?;
+                    if &crlf != b"\r\n" {
+                        return Err(invalid_data("invalid chunk end"));
+                    }
+                }
+                length
+            }
+            Framing::Close => reader.read(buf)?,
+        };
+
+        self.done = match self.framing {
+            Framing::Length(_) => self.remaining == 0,
+            _ => length == 0,
+        };
+        Ok(length)
+    }
+}
+
+/// Read at most remaining bytes, error if stream ends before
+fn read_limited(reader: &mut impl Read, remaining: &mut usize, buf: &mut [u8]) -> IoResult<usize> {
+    let limit = buf.len().min(*remaining);
+    let length = reader.read(&mut buf[..limit])?;
+    if length == 0 {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    *remaining -= length;
+    Ok(length)
+}
+
+fn invalid_data(message: &str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, message)
+}
+
+pub(crate) fn read_head(reader: &mut impl BufRead, method: HttpMethod) -> Result<ResponseHead> {
+    // skip interim responses
+    let (headers, status, version) = loop {
+        let (headers, status, version) = read_headers(reader)?;
+        if !(100..200).contains(&status) || status == 101 {
+            break (headers, status, version);
+        }
+    };
+
+    // connection is reusable unless closed by peer
+    let has_token = |name, token| {
+        headers.get(name).is_some_and(|values| {
+            values
+                .iter()
+                .flat_map(|value| value.split(','))
+                .any(|value| value.trim().eq_ignore_ascii_case(token))
+        })
+    };
+    let keep_alive = match version.as_str() {
+        "HTTP/1.1" => !has_token("connection", "close"),
+        _ => has_token("connection", "keep-alive"),
+    };
+
+    // body framing (RFC 9112 6.3)
+    let chunked = headers
+        .get("transfer-encoding")
+        .and_then(|values| values.last())
+        .and_then(|codings| codings.rsplit(',').next())
+        .map(|coding| coding.trim().eq_ignore_ascii_case("chunked"));
+    let content_length = match headers.get("content-length") {
+        Some(values) => Some(parse_content_length(values)?),
+        None => None,
+    };
+    let framing = if method == HttpMethod::Head || matches!(status, 100..200 | 204 | 304) {
+        Framing::Empty
+    } else if let Some(chunked) = chunked {
+        if chunked {
+            Framing::Chunked
+        } else {
+            Framing::Close
+        }
+    } else if let Some(content_length) = content_length {
+        Framing::Length(content_length)
+    } else {
+        Framing::Close
+    };
+
+    Ok(ResponseHead {
         status,
-        body,
+        headers,
+        framing,
+        keep_alive: keep_alive && status != 101,
     })
 }
 
-fn read_headers(
-    reader: &mut BufReader<&mut impl Read>,
-) -> Result<(HashMap<String, Vec<String>>, u16)> {
+fn read_headers(reader: &mut impl BufRead) -> Result<(HashMap<String, Vec<String>>, u16, String)> {
     let mut raw_header = String::new();
-    while reader.read_line(&mut raw_header)? > 2 {}
+    read_block(reader, &mut raw_header)?;
 
-    let mut headers: HashMap<String, Vec<String>> = HashMap::new();
     let status: u16 = raw_header
         .get(9..12)
         .and_then(|status| status.parse().ok())
         .ok_or_else(|| Fail::new("status code not u16"))?;
+    let version = raw_header.get(..8).unwrap_or_default().to_string();
+    let fields = raw_header.split_once("\r\n").unwrap_or_default().1;
 
-    let mut lines = 0;
-    let mut duplicate_headers = 0;
-    raw_header
-        .split("\r\n")
-        .filter_map(|line| {
-            lines += 1;
-            line.split_once(':')
-        })
-        .for_each(|(key, value)| {
-            let key = key.trim().to_lowercase();
-            let value = value.trim().to_lowercase();
-            if let Some(values) = headers.get_mut(&key) {
-                duplicate_headers += 1;
-                values.push(value);
-            } else {
-                headers.insert(key, vec![value]);
-            }
-        });
+    Ok((parse_fields(fields)?, status, version))
+}
 
-    if headers.len() < lines - duplicate_headers - 3 {
-        Fail::from("invalid header")?;
+/// Read lines until empty line, at most MAX_HEADER_SIZE bytes
+fn read_block(reader: &mut impl BufRead, raw: &mut String) -> IoResult<()> {
+    loop {
+        let start = raw.len();
+        let limit = MAX_HEADER_SIZE - start;
+        let length = reader.take(limit as u64).read_line(raw)?;
+        match &raw[start..] {
+            "\r\n" | "\n" => return Ok(()),
+            line if line.ends_with('\n') => {}
+            _ if length == limit => return Err(invalid_data("response header too large")),
+            _ => return Err(ErrorKind::UnexpectedEof.into()),
+        }
     }
+}
 
-    Ok((headers, status))
+/// Parse content-length, repeated values must be equal (RFC 9112 6.3)
+fn parse_content_length(values: &[String]) -> Result<usize> {
+    let mut content_length = None;
+    for value in values.iter().flat_map(|value| value.split(',')) {
+        let value = value.trim();
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Fail::from("invalid content-length");
+        }
+        let value = value
+            .parse()
+            .or_else(|_| Fail::from("invalid content-length"))?;
+        if content_length.is_some_and(|content_length| content_length != value) {
+            return Fail::from("conflicting content-length");
+        }
+        content_length = Some(value);
+    }
+    content_length.map_or_else(|| Fail::from("invalid content-length"), Ok)
+}
+
+fn parse_fields(raw: &str) -> Result<HashMap<String, Vec<String>>> {
+    let mut fields: HashMap<String, Vec<String>> = HashMap::new();
+    for line in raw.split("\r\n").filter(|line| !line.is_empty()) {
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| Fail::new("invalid header"))?;
+        let key = key.trim().to_lowercase();
+        let value = value.trim().to_string();
+        fields.entry(key).or_default().push(value);
+    }
+    Ok(fields)
 }
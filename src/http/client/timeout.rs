@@ -0,0 +1,121 @@
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{Error as IoError, ErrorKind};
+use std::time::{Duration, Instant};
+
+use crate::Error;
+
+/// Connect, read, write and total request timeouts (None waits forever)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timeouts {
+    pub connect: Option<Duration>,
+    pub read: Option<Duration>,
+    pub write: Option<Duration>,
+    pub total: Option<Duration>,
+}
+
+impl Timeouts {
+    /// Create new Timeouts without limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit time to establish TCP connection (name resolution is not limited)
+    pub fn connect(mut self, connect: Option<Duration>) -> Self {
+        self.connect = connect;
+        self
+    }
+
+    /// Limit time a single read may block
+    pub fn read(mut self, read: Option<Duration>) -> Self {
+        self.read = read;
+        self
+    }
+
+    /// Limit time a single write may block
+    pub fn write(mut self, write: Option<Duration>) -> Self {
+        self.write = write;
+        self
+    }
+
+    /// Limit time of whole request including redirects
+    pub fn total(mut self, total: Option<Duration>) -> Self {
+        self.total = total;
+        self
+    }
+}
+
+/// Which timeout elapsed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeoutKind {
+    Connect,
+    Read,
+    Write,
+    Total,
+}
+
+/// Error of elapsed timeout
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeoutError {
+    pub kind: TimeoutKind,
+}
+
+impl TimeoutError {
+    /// Wrap in io::Error of kind TimedOut
+    pub(crate) fn io(kind: TimeoutKind) -> IoError {
+        IoError::new(ErrorKind::TimedOut, TimeoutError { kind })
+    }
+
+    /// Unwrap TimeoutError of io::Error
+    pub(crate) fn unwrap_io(err: Error) -> Error {
+        match err
+            .downcast_ref::<IoError>()
+            .and_then(|err| err.get_ref())
+            .and_then(|err| err.downcast_ref::<TimeoutError>())
+        {
+            Some(timeout) => Box::new(*timeout),
+            None => err,
+        }
+    }
+}
+
+impl Display for TimeoutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let kind = match self.kind {
+            TimeoutKind::Connect => "connect",
+            TimeoutKind::Read => "read",
+            TimeoutKind::Write => "write",
+            TimeoutKind::Total => "request",
+        };
+        write!(f, "{kind} timed out")
+    }
+}
+
+impl StdError for TimeoutError {}
+
+/// Shortest of timeout and time left until deadline, error if deadline passed
+pub(crate) fn limit(
+    timeout: Option<Duration>,
+    deadline: Option<Instant>,
+) -> Result<Option<Duration>, IoError> {
+    let Some(deadline) = deadline else {
+        return Ok(timeout);
+    };
+    let remaining = deadline
+        .checked_duration_since(Instant::now())
+        .filter(|remaining| !remaining.is_zero())
+        .ok_or_else(|| TimeoutError::io(TimeoutKind::Total))?;
+    Ok(Some(
+        timeout.map_or(remaining, |timeout| timeout.min(remaining)),
+    ))
+}
+
+/// Whether io::Error is caused by a timeout
+pub(crate) fn is_timeout(err: &IoError) -> bool {
+    matches!(err.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock)
+}
+
+/// Whether error is caused by a timeout
+pub(crate) fn is_timeout_error(err: &Error) -> bool {
+    err.downcast_ref::<IoError>().is_some_and(is_timeout)
+}
@@ -0,0 +1,55 @@
+//! Per-request context of the current thread
+
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+thread_local! {
+    /// ID of request handled by the current thread
+    static REQUEST_ID: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Counter making generated IDs unique
+static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Get ID of the request handled by the current thread (propagated by HttpClient)
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.with(|id| id.borrow().clone())
+}
+
+/// Set ID of the request handled by the current thread until guard is dropped
+pub(crate) fn enter_request(id: &str) -> RequestGuard {
+    let previous = REQUEST_ID.with(|current| current.replace(Some(id.to_string())));
+    RequestGuard(previous)
+}
+
+/// Restores previous request ID when dropped
+pub(crate) struct RequestGuard(Option<String>);
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        REQUEST_ID.with(|current| *current.borrow_mut() = self.0.take());
+    }
+}
+
+/// Generate random 128-bit ID as 32 lowercase hex characters
+pub(crate) fn generate_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    let count = ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    // randomly seeded hashes of time and counter
+    let mut id = String::with_capacity(32);
+    for part in 0..2u64 {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(nanos);
+        hasher.write_u64(count);
+        hasher.write_u64(part);
+        id.push_str(&format!("{:016x}", hasher.finish()));
+    }
+    id
+}
@@ -1,18 +1,23 @@
+mod context;
+
 use std::error::Error;
 use std::io::{Read, Write};
 
 use crate::Fail;
 
+pub use context::*;
+
 pub trait ReadWrite: Read + Write {}
 
 impl<T: Read + Write> ReadWrite for T {}
 
 /// HTTP request method (GET or POST)
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum HttpMethod {
     Get,
     Post,
     Put,
+    Patch,
     Delete,
     Head,
     Connect,
@@ -27,6 +32,7 @@ impl HttpMethod {
             Get => "GET",
             Post => "POST",
             Put => "PUT",
+            Patch => "PATCH",
             Delete => "DELETE",
             Head => "HEAD",
             Connect => "CONNECT",
@@ -34,6 +40,14 @@ impl HttpMethod {
             Trace => "TRACE",
         }
     }
+
+    /// Whether repeating the request has the same effect (safe to retry)
+    pub fn is_idempotent(&self) -> bool {
+        !matches!(
+            self,
+            HttpMethod::Post | HttpMethod::Patch | HttpMethod::Connect
+        )
+    }
 }
 
 impl TryFrom<&str> for HttpMethod {
@@ -44,6 +58,7 @@ impl TryFrom<&str> for HttpMethod {
             "GET" => Ok(HttpMethod::Get),
             "POST" => Ok(HttpMethod::Post),
             "PUT" => Ok(HttpMethod::Put),
+            "PATCH" => Ok(HttpMethod::Patch),
             "DELETE" => Ok(HttpMethod::Delete),
             "HEAD" => Ok(HttpMethod::Head),
             "CONNECT" => Ok(HttpMethod::Connect),
@@ -0,0 +1,210 @@
+//! HTTP-date formatting and parsing (RFC 9110)
+
+use crate::{Fail, Result};
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAY_NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Format time as IMF-fixdate (e.g. "Sun, 06 Nov 1994 08:49:37 GMT")
+pub fn format_http_date(time: SystemTime) -> String {
+    // seconds since epoch, negative before 1970
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs_f64().ceil() as i64),
+    };
+    let (days, secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        DAY_NAMES[(days + 4).rem_euclid(7) as usize],
+        day,
+        MONTH_NAMES[month as usize - 1],
+        year,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Get current time as IMF-fixdate
+pub fn http_date_now() -> String {
+    format_http_date(SystemTime::now())
+}
+
+/// Parse HTTP-date (IMF-fixdate, obsolete RFC 850 and asctime formats)
+pub fn parse_http_date(date: &str) -> Result<SystemTime> {
+    let (day, month, year, time) = match date.split_once(',') {
+        // IMF-fixdate: Sun, 06 Nov 1994 08:49:37 GMT
+        Some((_, rest)) => match rest.split_whitespace().collect::<Vec<&str>>()[..] {
+            [day, month, year, time, "GMT"] => (day, month, date_digits(year, 4)?, time),
+
+            // RFC 850: Sunday, 06-Nov-94 08:49:37 GMT
+            [date, time, "GMT"] => match date.split('-').collect::<Vec<&str>>()[..] {
+                [day, month, year] => {
+                    let year = date_digits(year, 2)?;
+                    (day, month, if year < 70 { 2000 } else { 1900 } + year, time)
+                }
+                _ => return Fail::from("invalid HTTP-date"),
+            },
+            _ => return Fail::from("invalid HTTP-date"),
+        },
+
+        // asctime: Sun Nov  6 08:49:37 1994
+        None => match date.split_whitespace().collect::<Vec<&str>>()[..] {
+            [_, month, day, time, year] => (day, month, date_digits(year, 4)?, time),
+            _ => return Fail::from("invalid HTTP-date"),
+        },
+    };
+
+    // month and day
+    let month = MONTH_NAMES
+        .iter()
+        .position(|name| *name == month)
+        .ok_or_else(|| Fail::new("invalid month in HTTP-date"))? as i64
+        + 1;
+    let day = leading_digits(day, 1, 2)
+        .filter(|(_, rest)| rest.is_empty())
+        .ok_or_else(|| Fail::new("invalid day in HTTP-date"))?
+        .0;
+    let days = days_from_civil(year, month, day);
+    if civil_from_days(days) != (year, month, day) {
+        return Fail::from("invalid day in HTTP-date");
+    }
+
+    // time of day
+    let time = time
+        .split(':')
+        .map(|field| date_digits(field, 2))
+        .collect::<Result<Vec<i64>>>()?;
+    let [hour, minute, second] = time[..] else {
+        return Fail::from("invalid time in HTTP-date");
+    };
+    if hour > 23 || minute > 59 || second > 60 {
+        return Fail::from("invalid time in HTTP-date");
+    }
+
+    // seconds since epoch
+    system_time(days, hour, minute, second).map_or_else(|| Fail::from("invalid HTTP-date"), Ok)
+}
+
+/// Parse field of HTTP-date consisting of exactly length digits
+fn date_digits(field: &str, length: usize) -> Result<i64> {
+    match leading_digits(field, length, length) {
+        Some((value, "")) => Ok(value),
+        _ => Fail::from("invalid HTTP-date"),
+    }
+}
+
+/// Parse date of Set-Cookie Expires attribute leniently (RFC 6265 section 5.1.1)
+pub fn parse_cookie_date(date: &str) -> Result<SystemTime> {
+    let (mut time, mut day, mut month, mut year) = (None, None, None, None);
+    let delimiter = |c: char| matches!(c, '\x09' | '\x20'..='\x2f' | '\x3b'..='\x40' | '\x5b'..='\x60' | '\x7b'..='\x7e');
+
+    // first token matching each field wins
+    for token in date.split(delimiter).filter(|token| !token.is_empty()) {
+        if time.is_none()
+            && let Some(parsed) = cookie_time(token)
+        {
+            time = Some(parsed);
+        } else if day.is_none()
+            && let Some((parsed, _)) = leading_digits(token, 1, 2)
+        {
+            day = Some(parsed);
+        } else if month.is_none()
+            && let Some(position) = MONTH_NAMES.iter().position(|name| {
+                token
+                    .get(..3)
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case(name))
+            })
+        {
+            month = Some(position as i64 + 1);
+        } else if year.is_none()
+            && let Some((parsed, _)) = leading_digits(token, 2, 4)
+        {
+            year = Some(parsed);
+        }
+    }
+
+    // two-digit years and ranges
+    let (Some([hour, minute, second]), Some(day), Some(month), Some(year)) =
+        (time, day, month, year)
+    else {
+        return Fail::from("invalid cookie date");
+    };
+    let year = match year {
+        70..=99 => year + 1900,
+        0..=69 => year + 2000,
+        _ => year,
+    };
+    let days = days_from_civil(year, month, day);
+    if year < 1601
+        || hour > 23
+        || minute > 59
+        || second > 59
+        || civil_from_days(days) != (year, month, day)
+    {
+        return Fail::from("invalid cookie date");
+    }
+
+    // seconds since epoch
+    system_time(days, hour, minute, second).map_or_else(|| Fail::from("invalid cookie date"), Ok)
+}
+
+/// Time of days since epoch and time of day, None on overflow
+fn system_time(days: i64, hour: i64, minute: i64, second: i64) -> Option<SystemTime> {
+    let secs = days
+        .checked_mul(86400)?
+        .checked_add(hour * 3600 + minute * 60 + second)?;
+    match secs {
+        0.. => UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64)),
+        _ => UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs())),
+    }
+}
+
+/// Parse hh:mm:ss of cookie date, trailing non-digits allowed
+fn cookie_time(token: &str) -> Option<[i64; 3]> {
+    let mut fields = token.splitn(3, ':');
+    let hour = leading_digits(fields.next()?, 1, 2).filter(|(_, rest)| rest.is_empty())?;
+    let minute = leading_digits(fields.next()?, 1, 2).filter(|(_, rest)| rest.is_empty())?;
+    let (second, _) = leading_digits(fields.next()?, 1, 2)?;
+    Some([hour.0, minute.0, second])
+}
+
+/// Parse min to max leading digits followed by non-digits
+fn leading_digits(token: &str, min: usize, max: usize) -> Option<(i64, &str)> {
+    let length = token.bytes().take_while(u8::is_ascii_digit).count();
+    if !(min..=max).contains(&length) {
+        return None;
+    }
+    Some((token[..length].parse().ok()?, &token[length..]))
+}
+
+/// Days since 1970-01-01 of civil date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Civil date (year, month, day) of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -1,7 +1,11 @@
 //! Lightweight HTTP library
 pub mod client;
 mod common;
+pub mod date;
 pub mod server;
+pub mod url;
+
+pub use common::current_request_id;
 
 use crate::meta::{init_name, init_version, name as get_name, version as get_version};
 
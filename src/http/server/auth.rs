@@ -0,0 +1,106 @@
+//! HTTP Basic and Bearer authentication
+
+use super::{HttpRequest, ResponseData, respond};
+use crate::http::url::percent_decode;
+
+/// Verifies Basic credentials (user, password)
+pub type BasicVerifier = fn(&str, &str) -> bool;
+
+/// Verifies Bearer token
+pub type BearerVerifier = fn(&str) -> bool;
+
+/// Authentication scheme with credential verifier
+#[derive(Clone, Debug)]
+pub enum Authenticator {
+    Basic(BasicVerifier),
+    Bearer(BearerVerifier),
+}
+
+/// Authentication required for path prefixes
+#[derive(Clone, Debug)]
+pub struct AuthPolicy {
+    realm: String,
+    routes: Vec<(String, Authenticator)>,
+}
+
+impl AuthPolicy {
+    /// Create new AuthPolicy without protected paths
+    pub fn new(realm: impl ToString) -> Self {
+        Self {
+            realm: realm.to_string(),
+            routes: Vec::new(),
+        }
+    }
+
+    /// Protect path prefix (e.g. "/admin") with Basic authentication
+    pub fn basic(self, prefix: impl ToString, verifier: BasicVerifier) -> Self {
+        self.protect(prefix, Authenticator::Basic(verifier))
+    }
+
+    /// Protect path prefix (e.g. "/api") with Bearer authentication
+    pub fn bearer(self, prefix: impl ToString, verifier: BearerVerifier) -> Self {
+        self.protect(prefix, Authenticator::Bearer(verifier))
+    }
+
+    /// Protect path prefix with authenticator
+    pub fn protect(mut self, prefix: impl ToString, authenticator: Authenticator) -> Self {
+        let prefix = prefix.to_string();
+        let prefix = prefix.trim_end_matches('/');
+        self.routes.push((prefix.to_string(), authenticator));
+        self
+    }
+
+    /// Get authenticator of the longest prefix matching the normalized path
+    pub fn authenticator(&self, path: &str) -> Option<&Authenticator> {
+        let path = normalize_path(path);
+        self.routes
+            .iter()
+            .filter(|(prefix, _)| {
+                path.strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, authenticator)| authenticator)
+    }
+
+    /// Check request credentials, returns 401 response if unauthorized
+    pub(crate) fn check(&self, request: &HttpRequest) -> Option<Vec<u8>> {
+        let realm = self.realm.replace('\\', "\\\\").replace('"', "\\\"");
+        let challenge = match self.authenticator(request.url())? {
+            Authenticator::Basic(verifier) => match request.basic_auth() {
+                Some((user, password)) if verifier(&user, &password) => return None,
+                _ => format!("Basic realm=\"{realm}\", charset=\"UTF-8\""),
+            },
+            Authenticator::Bearer(verifier) => match request.bearer_token() {
+                Some(token) if verifier(token) => return None,
+                Some(_) => format!("Bearer realm=\"{realm}\", error=\"invalid_token\""),
+                None => format!("Bearer realm=\"{realm}\""),
+            },
+        };
+
+        // respond with challenge
+        Some(respond(
+            "Unauthorized",
+            "text/plain",
+            ResponseData::unauthorized()
+                .header("www-authenticate", &challenge)
+                .build(),
+        ))
+    }
+}
+
+/// Percent-decode path, collapse duplicate slashes and resolve dot-segments
+fn normalize_path(path: &str) -> String {
+    let decoded = String::from_utf8_lossy(&percent_decode(path)).to_string();
+    let mut segments = Vec::new();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    format!("/{}", segments.join("/"))
+}
@@ -2,7 +2,13 @@ use std::sync::Arc;
 
 use crate::Result;
 
-use super::{ErrorHandler, Handler, HttpServer, HttpSettings, ResponseData, respond};
+use super::{
+    ErrorContext, ErrorHandler, Handler, HttpServer, HttpSettings, Listener, ResponseData,
+    ServerListener, respond,
+};
+
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
 
 #[cfg(feature = "tls")]
 use super::TlsConfigProvider;
@@ -11,11 +17,59 @@ use super::TlsConfigProvider;
 #[derive(Clone, Debug)]
 pub struct HttpServerBuilder {
     addr: String,
+    #[cfg(unix)]
+    unix_socket: Option<(PathBuf, Option<u32>)>,
     settings: HttpSettings,
     handler: Handler,
     error_handler: ErrorHandler,
     #[cfg(feature = "tls")]
     tls_config: Option<TlsConfigProvider>,
+    additional: Vec<Bind>,
+    #[cfg(unix)]
+    systemd: bool,
+}
+
+/// Respond with error status, hides messages of internal errors
+pub(crate) fn default_error_handler(context: ErrorContext) -> Vec<u8> {
+    respond(
+        context.public_message(),
+        "text/plain",
+        ResponseData::new().status(context.status).build(),
+    )
+}
+
+/// Additional listener to bind on build
+#[derive(Clone, Debug)]
+struct Bind {
+    target: BindTarget,
+    #[cfg(feature = "tls")]
+    tls_config: Option<Option<TlsConfigProvider>>,
+}
+
+/// Address or path of additional listener
+#[derive(Clone, Debug)]
+enum BindTarget {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(PathBuf, Option<u32>),
+}
+
+impl Bind {
+    /// Bind listener using the default TLS configuration if not overridden
+    fn bind(
+        self,
+        #[cfg(feature = "tls")] tls_config: &Option<TlsConfigProvider>,
+    ) -> Result<ServerListener> {
+        let listener = match self.target {
+            BindTarget::Tcp(addr) => Listener::bind(addr)?,
+            #[cfg(unix)]
+            BindTarget::Unix(path, mode) => Listener::bind_unix(path, mode)?,
+        };
+        let listener = ServerListener::new(listener);
+        #[cfg(feature = "tls")]
+        let listener = listener.tls(self.tls_config.unwrap_or_else(|| tls_config.clone()));
+        Ok(listener)
+    }
 }
 
 impl Default for HttpServerBuilder {
@@ -29,17 +83,16 @@ impl HttpServerBuilder {
     pub fn new() -> Self {
         Self {
             addr: "localhost:8080".to_string(),
+            #[cfg(unix)]
+            unix_socket: None,
             settings: HttpSettings::default(),
             handler: |_| unimplemented!(),
-            error_handler: |err| {
-                respond(
-                    err.to_string(),
-                    "text/plain",
-                    ResponseData::internal_server_error().build(),
-                )
-            },
+            error_handler: default_error_handler,
             #[cfg(feature = "tls")]
             tls_config: None,
+            additional: Vec::new(),
+            #[cfg(unix)]
+            systemd: false,
         }
     }
 
@@ -49,6 +102,57 @@ impl HttpServerBuilder {
         self
     }
 
+    #[cfg(unix)]
+    /// Listen on Unix domain socket instead of TcpListener address<br>
+    /// mode sets the socket file permissions (e.g. Some(0o660))
+    pub fn unix(mut self, path: impl AsRef<Path>, mode: Option<u32>) -> Self {
+        self.unix_socket = Some((path.as_ref().to_path_buf(), mode));
+        self
+    }
+
+    /// Add TcpListener address (uses the TLS configuration of the builder)
+    pub fn listen(mut self, addr: impl ToString) -> Self {
+        self.additional.push(Bind {
+            target: BindTarget::Tcp(addr.to_string()),
+            #[cfg(feature = "tls")]
+            tls_config: None,
+        });
+        self
+    }
+
+    #[cfg(feature = "tls")]
+    /// Add TcpListener address with its own TLS configuration (plaintext when None)
+    pub fn listen_tls(
+        mut self,
+        addr: impl ToString,
+        tls_config: Option<TlsConfigProvider>,
+    ) -> Self {
+        self.additional.push(Bind {
+            target: BindTarget::Tcp(addr.to_string()),
+            tls_config: Some(tls_config),
+        });
+        self
+    }
+
+    #[cfg(unix)]
+    /// Add Unix domain socket listener (uses the TLS configuration of the builder)
+    pub fn listen_unix(mut self, path: impl AsRef<Path>, mode: Option<u32>) -> Self {
+        self.additional.push(Bind {
+            target: BindTarget::Unix(path.as_ref().to_path_buf(), mode),
+            #[cfg(feature = "tls")]
+            tls_config: None,
+        });
+        self
+    }
+
+    #[cfg(unix)]
+    /// Adopt sockets passed by systemd socket activation (LISTEN_FDS) instead of binding addr<br>
+    /// Falls back to addr (or unix) if no sockets were passed
+    pub fn systemd(mut self, systemd: bool) -> Self {
+        self.systemd = systemd;
+        self
+    }
+
     /// Set HttpSettings
     pub fn settings(mut self, settings: HttpSettings) -> Self {
         self.settings = settings;
@@ -90,13 +194,48 @@ impl HttpServerBuilder {
 
     /// Build HttpServer
     pub fn build(self) -> Result<Arc<HttpServer>> {
-        HttpServer::new(
-            self.addr,
+        // adopt systemd sockets
+        #[cfg(unix)]
+        let mut listeners = match self.systemd {
+            true => Listener::systemd()?,
+            false => Vec::new(),
+        };
+        #[cfg(not(unix))]
+        let mut listeners = Vec::new();
+
+        // bind primary listener
+        if listeners.is_empty() {
+            #[cfg(unix)]
+            listeners.push(match self.unix_socket {
+                Some((path, mode)) => Listener::bind_unix(path, mode)?,
+                None => Listener::bind(self.addr)?,
+            });
+            #[cfg(not(unix))]
+            listeners.push(Listener::bind(self.addr)?);
+        }
+        let mut listeners: Vec<ServerListener> = listeners
+            .into_iter()
+            .map(|listener| {
+                let listener = ServerListener::new(listener);
+                #[cfg(feature = "tls")]
+                let listener = listener.tls(self.tls_config.clone());
+                listener
+            })
+            .collect();
+
+        // bind additional listeners
+        for bind in self.additional {
+            listeners.push(bind.bind(
+                #[cfg(feature = "tls")]
+                &self.tls_config,
+            )?);
+        }
+
+        HttpServer::with_listeners(
+            listeners,
             Arc::new(self.settings),
             self.handler,
             self.error_handler,
-            #[cfg(feature = "tls")]
-            self.tls_config,
         )
     }
 }
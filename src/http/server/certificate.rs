@@ -0,0 +1,270 @@
+//! Client certificate information
+
+use crate::{Fail, Result};
+
+use rustls::crypto::CryptoProvider;
+use rustls::crypto::aws_lc_rs::default_provider;
+use rustls::crypto::hash::HashAlgorithm;
+use rustls_pki_types::CertificateDer;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Verified client certificate chain (mutual TLS)
+#[derive(Clone, Debug)]
+pub struct ClientCertificate {
+    chain: Vec<Vec<u8>>,
+    subject: Attributes,
+    subject_alt_names: Vec<String>,
+    fingerprint: String,
+}
+
+impl ClientCertificate {
+    /// Parse client certificate chain (end-entity certificate first)
+    pub fn from_chain(chain: &[CertificateDer]) -> Result<Self> {
+        let end_entity = chain
+            .first()
+            .ok_or_else(|| Fail::new("empty client certificate chain"))?;
+        let (subject, subject_alt_names) = parse_certificate(end_entity)?;
+        let fingerprint = sha256(end_entity)?
+            .as_ref()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+
+        Ok(Self {
+            chain: chain.iter().map(|cert| cert.to_vec()).collect(),
+            subject,
+            subject_alt_names,
+            fingerprint,
+        })
+    }
+
+    /// Get DER encoded certificate chain (end-entity certificate first)
+    pub fn chain(&self) -> &[Vec<u8>] {
+        &self.chain
+    }
+
+    /// Get subject as string (e.g. "C=DE, O=Example, CN=client")
+    pub fn subject(&self) -> String {
+        self.subject
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Get subject attributes as (name, value) pairs
+    pub fn subject_attributes(&self) -> &[(String, String)] {
+        &self.subject
+    }
+
+    /// Get subject common name (CN)
+    pub fn common_name(&self) -> Option<&str> {
+        self.subject
+            .iter()
+            .find(|(key, _)| key == "CN")
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get subject alternative names (e.g. "DNS:example.com", "IP:127.0.0.1")
+    pub fn subject_alt_names(&self) -> &[String] {
+        &self.subject_alt_names
+    }
+
+    /// Get lowercase hex SHA-256 fingerprint of the end-entity certificate
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+}
+
+/// SHA-256 digest using the process default crypto provider (aws-lc-rs if none installed)
+fn sha256(data: &[u8]) -> Result<impl AsRef<[u8]>> {
+    let fallback;
+    let provider = match CryptoProvider::get_default() {
+        Some(provider) => provider.as_ref(),
+        None => {
+            fallback = default_provider();
+            &fallback
+        }
+    };
+    let hash = provider
+        .cipher_suites
+        .iter()
+        .filter_map(|suite| suite.tls13())
+        .map(|suite| suite.common.hash_provider)
+        .find(|hash| hash.algorithm() == HashAlgorithm::SHA256)
+        .ok_or_else(|| Fail::new("crypto provider without SHA-256"))?;
+    Ok(hash.hash(data))
+}
+
+/// Name attributes as (name, value) pairs
+type Attributes = Vec<(String, String)>;
+
+/// DER encoded element (tag and content)
+struct Der<'a> {
+    tag: u8,
+    content: &'a [u8],
+}
+
+/// Read next DER element and return it with the remaining bytes
+fn read_der(data: &[u8]) -> Result<(Der<'_>, &[u8])> {
+    // tag and first length byte
+    let (&tag, rest) = data
+        .split_first()
+        .ok_or_else(|| Fail::new("broken certificate"))?;
+    let (&len_byte, rest) = rest
+        .split_first()
+        .ok_or_else(|| Fail::new("broken certificate"))?;
+
+    // short or long form length
+    let (len, rest) = if len_byte < 0x80 {
+        (len_byte as usize, rest)
+    } else {
+        let num = (len_byte & 0x7f) as usize;
+        if num == 0 || num > 4 || rest.len() < num {
+            return Fail::from("broken certificate");
+        }
+        let len = rest[..num]
+            .iter()
+            .fold(0usize, |len, &b| (len << 8) | b as usize);
+        (len, &rest[num..])
+    };
+
+    // split content
+    if rest.len() < len {
+        return Fail::from("broken certificate");
+    }
+    let (content, rest) = rest.split_at(len);
+    Ok((Der { tag, content }, rest))
+}
+
+/// Read all DER elements
+fn read_all_der(mut data: &[u8]) -> Result<Vec<Der<'_>>> {
+    let mut elements = Vec::new();
+    while !data.is_empty() {
+        let (element, rest) = read_der(data)?;
+        elements.push(element);
+        data = rest;
+    }
+    Ok(elements)
+}
+
+/// Parse subject and subject alternative names from DER certificate
+fn parse_certificate(cert: &[u8]) -> Result<(Attributes, Vec<String>)> {
+    // Certificate -> TBSCertificate
+    let (certificate, _) = read_der(cert)?;
+    let (tbs, _) = read_der(certificate.content)?;
+    let mut fields = read_all_der(tbs.content)?.into_iter().peekable();
+
+    // skip optional version
+    if fields.peek().is_some_and(|field| field.tag == 0xa0) {
+        fields.next();
+    }
+
+    // serial number, signature, issuer, validity, subject
+    let subject = fields
+        .nth(4)
+        .ok_or_else(|| Fail::new("missing subject in certificate"))?;
+    let subject = parse_name(subject.content)?;
+
+    // find extensions
+    let mut subject_alt_names = Vec::new();
+    if let Some(extensions) = fields.find(|field| field.tag == 0xa3) {
+        let (extensions, _) = read_der(extensions.content)?;
+        for extension in read_all_der(extensions.content)? {
+            let parts = read_all_der(extension.content)?;
+            if let (Some(oid), Some(value)) = (parts.first(), parts.last())
+                && oid.content == [0x55, 0x1d, 0x11]
+            {
+                let (names, _) = read_der(value.content)?;
+                subject_alt_names = parse_general_names(names.content)?;
+            }
+        }
+    }
+
+    Ok((subject, subject_alt_names))
+}
+
+/// Parse X.501 name to (attribute, value) pairs
+fn parse_name(name: &[u8]) -> Result<Attributes> {
+    let mut attributes = Vec::new();
+    for set in read_all_der(name)? {
+        for attribute in read_all_der(set.content)? {
+            let parts = read_all_der(attribute.content)?;
+            if let (Some(oid), Some(value)) = (parts.first(), parts.get(1)) {
+                attributes.push((
+                    attribute_name(oid.content),
+                    String::from_utf8_lossy(value.content).to_string(),
+                ));
+            }
+        }
+    }
+    Ok(attributes)
+}
+
+/// Short name of attribute type OID
+fn attribute_name(oid: &[u8]) -> String {
+    match oid {
+        [0x55, 0x04, 0x03] => "CN".to_string(),
+        [0x55, 0x04, 0x05] => "serialNumber".to_string(),
+        [0x55, 0x04, 0x06] => "C".to_string(),
+        [0x55, 0x04, 0x07] => "L".to_string(),
+        [0x55, 0x04, 0x08] => "ST".to_string(),
+        [0x55, 0x04, 0x09] => "street".to_string(),
+        [0x55, 0x04, 0x0a] => "O".to_string(),
+        [0x55, 0x04, 0x0b] => "OU".to_string(),
+        [0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x19] => "DC".to_string(),
+        [0x09, 0x92, 0x26, 0x89, 0x93, 0xf2, 0x2c, 0x64, 0x01, 0x01] => "UID".to_string(),
+        [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01] => "emailAddress".to_string(),
+        _ => oid_string(oid),
+    }
+}
+
+/// Dotted string representation of OID
+fn oid_string(oid: &[u8]) -> String {
+    let mut parts = Vec::new();
+    let mut value = 0u64;
+    for &b in oid {
+        value = (value << 7) | (b & 0x7f) as u64;
+        if b & 0x80 == 0 {
+            if parts.is_empty() {
+                let first = (value / 40).min(2);
+                parts.push(first);
+                parts.push(value - first * 40);
+            } else {
+                parts.push(value);
+            }
+            value = 0;
+        }
+    }
+    parts
+        .iter()
+        .map(|part| part.to_string())
+        .collect::<Vec<String>>()
+        .join(".")
+}
+
+/// Parse GeneralNames of subject alternative name extension
+fn parse_general_names(names: &[u8]) -> Result<Vec<String>> {
+    let mut parsed = Vec::new();
+    for name in read_all_der(names)? {
+        let value = String::from_utf8_lossy(name.content);
+        match name.tag {
+            0x81 => parsed.push(format!("email:{value}")),
+            0x82 => parsed.push(format!("DNS:{value}")),
+            0x86 => parsed.push(format!("URI:{value}")),
+            0x87 => match name.content.len() {
+                4 => {
+                    let ip: [u8; 4] = name.content.try_into()?;
+                    parsed.push(format!("IP:{}", Ipv4Addr::from(ip)));
+                }
+                16 => {
+                    let ip: [u8; 16] = name.content.try_into()?;
+                    parsed.push(format!("IP:{}", Ipv6Addr::from(ip)));
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    Ok(parsed)
+}
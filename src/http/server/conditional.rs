@@ -0,0 +1,134 @@
+//! Conditional requests (RFC 9110 section 13)
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{HttpMethod, HttpRequest, ResponseData, respond};
+use crate::http::date::{format_http_date, parse_http_date};
+
+/// Evaluate If-Match, If-Unmodified-Since, If-None-Match and If-Modified-Since<br>
+/// Validators are ignored if the resource has no current representation (exists is false)<br>
+/// Returns 304 Not Modified or 412 Precondition Failed response if a precondition applies
+pub fn conditional_response(
+    request: &HttpRequest,
+    exists: bool,
+    etag: Option<&str>,
+    last_modified: Option<SystemTime>,
+) -> Option<Vec<u8>> {
+    let headers = request.headers();
+    let (etag, last_modified) = match exists {
+        true => (etag, last_modified.map(truncate_secs)),
+        false => (None, None),
+    };
+    let safe = matches!(request.method(), HttpMethod::Get | HttpMethod::Head);
+
+    // If-Match or If-Unmodified-Since
+    if let Some(if_match) = headers.get("if-match") {
+        if !matches_etag(if_match, exists, etag, true) {
+            return Some(precondition_failed());
+        }
+    } else if let (Some(since), Some(last_modified)) = (
+        headers
+            .get("if-unmodified-since")
+            .map(|date| parse_http_date(date)),
+        last_modified,
+    ) && since.is_ok_and(|since| last_modified > since)
+    {
+        return Some(precondition_failed());
+    }
+
+    // If-None-Match or If-Modified-Since
+    if let Some(if_none_match) = headers.get("if-none-match") {
+        if matches_etag(if_none_match, exists, etag, false) {
+            return Some(match safe {
+                true => not_modified(etag, last_modified),
+                false => precondition_failed(),
+            });
+        }
+    } else if let (true, Some(since), Some(last_modified)) = (
+        safe,
+        headers
+            .get("if-modified-since")
+            .map(|date| parse_http_date(date)),
+        last_modified,
+    ) && since.is_ok_and(|since| last_modified <= since)
+    {
+        return Some(not_modified(etag, Some(last_modified)));
+    }
+
+    // no precondition applies
+    None
+}
+
+/// Check whether entity-tag list matches etag (strong or weak comparison)<br>
+/// "*" matches any current representation, even without etag (RFC 9110 13.1.1-2)
+fn matches_etag(list: &str, exists: bool, etag: Option<&str>, strong: bool) -> bool {
+    if list.trim() == "*" {
+        return exists;
+    }
+    let Some(etag) = etag else {
+        return false;
+    };
+    let (weak, opaque) = split_etag(etag);
+    parse_etags(list).iter().any(|&tag| {
+        let (tag_weak, tag_opaque) = split_etag(tag);
+        tag_opaque == opaque && !(strong && (weak || tag_weak))
+    })
+}
+
+/// Split entity-tag into weakness and opaque tag
+fn split_etag(etag: &str) -> (bool, &str) {
+    match etag.strip_prefix("W/") {
+        Some(opaque) => (true, opaque),
+        None => (false, etag),
+    }
+}
+
+/// Parse comma separated entity-tags (quoted tags may contain commas)
+fn parse_etags(list: &str) -> Vec<&str> {
+    let mut etags = Vec::new();
+    let mut rest = list;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        let start = rest.strip_prefix("W/").unwrap_or(rest);
+        let Some(quoted) = start.strip_prefix('"') else {
+            break;
+        };
+        let Some(end) = quoted.find('"') else {
+            break;
+        };
+        let len = rest.len() - quoted.len() + end + 1;
+        etags.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+    etags
+}
+
+/// Truncate time to seconds (precision of HTTP-date)
+fn truncate_secs(time: SystemTime) -> SystemTime {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => UNIX_EPOCH + std::time::Duration::from_secs(duration.as_secs()),
+        Err(_) => time,
+    }
+}
+
+/// 304 response with validators
+fn not_modified(etag: Option<&str>, last_modified: Option<SystemTime>) -> Vec<u8> {
+    let last_modified = last_modified.map(format_http_date);
+    let mut data = ResponseData::not_modified();
+    if let Some(etag) = etag {
+        data = data.header("etag", etag);
+    }
+    if let Some(last_modified) = &last_modified {
+        data = data.header("last-modified", last_modified);
+    }
+    respond(b"", "text/plain", data.build())
+}
+
+/// 412 response
+fn precondition_failed() -> Vec<u8> {
+    respond(
+        "Precondition Failed",
+        "text/plain",
+        ResponseData::precondition_failed().build(),
+    )
+}
@@ -0,0 +1,191 @@
+//! Cross-origin resource sharing (CORS)
+
+use super::{HttpMethod, HttpRequest, ResponseData, add_vary, insert_headers, respond};
+
+/// CORS policy answering preflight requests and decorating responses
+#[derive(Clone, Debug)]
+pub struct CorsPolicy {
+    origins: Vec<String>,
+    methods: Vec<String>,
+    allow_headers: Vec<String>,
+    expose_headers: Vec<String>,
+    credentials: bool,
+    max_age: Option<u64>,
+}
+
+impl Default for CorsPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CorsPolicy {
+    /// Create new CorsPolicy without allowed origins (methods GET, HEAD and POST)
+    pub fn new() -> Self {
+        Self {
+            origins: Vec::new(),
+            methods: vec!["GET".to_string(), "HEAD".to_string(), "POST".to_string()],
+            allow_headers: Vec::new(),
+            expose_headers: Vec::new(),
+            credentials: false,
+            max_age: None,
+        }
+    }
+
+    /// Allow origin (e.g. "https://example.com", "https://*.example.com" or "*" for any)
+    pub fn origin(mut self, origin: impl ToString) -> Self {
+        self.origins.push(origin.to_string());
+        self
+    }
+
+    /// Allow any origin
+    pub fn any_origin(self) -> Self {
+        self.origin("*")
+    }
+
+    /// Set allowed methods
+    pub fn methods(mut self, methods: &[HttpMethod]) -> Self {
+        self.methods = methods
+            .iter()
+            .map(|method| method.as_str().to_string())
+            .collect();
+        self
+    }
+
+    /// Allow request header ("*" allows all requested headers)
+    pub fn allow_header(mut self, header: impl ToString) -> Self {
+        self.allow_headers.push(header.to_string().to_lowercase());
+        self
+    }
+
+    /// Expose response header to scripts
+    pub fn expose_header(mut self, header: impl ToString) -> Self {
+        self.expose_headers.push(header.to_string());
+        self
+    }
+
+    /// Allow credentials (cookies, authorization)
+    pub fn credentials(mut self, credentials: bool) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Set how long preflight results may be cached in seconds
+    pub fn max_age(mut self, max_age: Option<u64>) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Check whether origin is allowed
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        self.origins
+            .iter()
+            .any(|pattern| matches_pattern(pattern, origin))
+    }
+
+    /// Answer preflight request, None if request is no preflight
+    pub(crate) fn preflight(&self, request: &HttpRequest) -> Option<Vec<u8>> {
+        // OPTIONS with origin and requested method
+        let headers = request.headers();
+        let (Some(origin), Some(method)) = (
+            headers.get("origin"),
+            headers.get("access-control-request-method"),
+        ) else {
+            return None;
+        };
+        if request.method() != &HttpMethod::Options {
+            return None;
+        }
+
+        // check origin, method and headers
+        let requested_headers = headers
+            .get("access-control-request-headers")
+            .copied()
+            .unwrap_or_default();
+        let headers_allowed = self.allow_headers.iter().any(|header| header == "*")
+            || requested_headers
+                .split(',')
+                .map(|header| header.trim().to_lowercase())
+                .filter(|header| !header.is_empty())
+                .all(|header| self.allow_headers.contains(&header));
+        if !self.allows_origin(origin)
+            || !self.methods.iter().any(|allowed| allowed == method)
+            || !headers_allowed
+        {
+            return Some(respond(
+                b"",
+                "text/plain",
+                ResponseData::forbidden().build(),
+            ));
+        }
+
+        // preflight headers
+        let methods = self.methods.join(", ");
+        let max_age = self.max_age.map(|max_age| max_age.to_string());
+        let mut cors_headers = self.origin_headers(origin);
+        cors_headers.push(("access-control-allow-methods", &methods));
+        if !requested_headers.is_empty() {
+            cors_headers.push(("access-control-allow-headers", requested_headers));
+        }
+        if let Some(max_age) = &max_age {
+            cors_headers.push(("access-control-max-age", max_age));
+        }
+
+        // respond without content
+        let mut response = respond(b"", "text/plain", ResponseData::no_content().build());
+        insert_headers(&mut response, &cors_headers);
+        self.vary(&mut response);
+        Some(response)
+    }
+
+    /// Add CORS headers to response if origin is allowed
+    pub(crate) fn decorate(&self, origin: &str, response: &mut Vec<u8>) {
+        if !self.allows_origin(origin) {
+            return;
+        }
+        let expose_headers = self.expose_headers.join(", ");
+        let mut cors_headers = self.origin_headers(origin);
+        if !expose_headers.is_empty() {
+            cors_headers.push(("access-control-expose-headers", &expose_headers));
+        }
+        insert_headers(response, &cors_headers);
+        self.vary(response);
+    }
+
+    /// Whether the request origin is reflected instead of "*"
+    fn reflects_origin(&self) -> bool {
+        self.credentials || !self.origins.iter().any(|pattern| pattern == "*")
+    }
+
+    /// Add Origin to Vary header (also if set by the handler) if origin is reflected
+    fn vary(&self, response: &mut Vec<u8>) {
+        if self.reflects_origin() {
+            add_vary(response, "Origin");
+        }
+    }
+
+    /// Allow-Origin and Allow-Credentials headers
+    fn origin_headers<'a>(&self, origin: &'a str) -> Vec<(&'static str, &'a str)> {
+        let mut headers = Vec::new();
+        match self.reflects_origin() {
+            true => headers.push(("access-control-allow-origin", origin)),
+            false => headers.push(("access-control-allow-origin", "*")),
+        }
+        if self.credentials {
+            headers.push(("access-control-allow-credentials", "true"));
+        }
+        headers
+    }
+}
+
+/// Match origin against pattern with at most one wildcard
+fn matches_pattern(pattern: &str, origin: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, suffix)) => {
+            origin.len() > prefix.len() + suffix.len()
+                && origin.starts_with(prefix)
+                && origin.ends_with(suffix)
+        }
+        None => pattern.eq_ignore_ascii_case(origin),
+    }
+}
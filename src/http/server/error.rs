@@ -0,0 +1,137 @@
+//! HTTP errors with status codes
+
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::Error;
+
+use super::HttpMethod;
+
+/// Status of errors without HttpError
+const INTERNAL_SERVER_ERROR: &str = "500 Internal Server Error";
+
+/// Error with HTTP status, message is safe to show to clients
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HttpError {
+    status: &'static str,
+    message: String,
+}
+
+impl HttpError {
+    /// Create new HttpError with status (e.g. "400 Bad Request") and message
+    pub fn new(status: &'static str, message: impl ToString) -> Self {
+        Self {
+            status,
+            message: message.to_string(),
+        }
+    }
+
+    /// Create boxed Error with status and message
+    pub fn boxed(status: &'static str, message: impl ToString) -> Error {
+        Box::new(Self::new(status, message))
+    }
+
+    /// 400 Bad Request
+    pub fn bad_request(message: impl ToString) -> Error {
+        Self::boxed("400 Bad Request", message)
+    }
+
+    /// 405 Method Not Allowed
+    pub fn method_not_allowed(message: impl ToString) -> Error {
+        Self::boxed("405 Method Not Allowed", message)
+    }
+
+    /// 413 Content Too Large
+    pub fn content_too_large(message: impl ToString) -> Error {
+        Self::boxed("413 Content Too Large", message)
+    }
+
+    /// 431 Request Header Fields Too Large
+    pub fn header_too_large(message: impl ToString) -> Error {
+        Self::boxed("431 Request Header Fields Too Large", message)
+    }
+
+    /// Get status (e.g. "400 Bad Request")
+    pub fn status(&self) -> &'static str {
+        self.status
+    }
+
+    /// Get status code
+    pub fn code(&self) -> u16 {
+        status_code(self.status)
+    }
+
+    /// Get message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for HttpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl StdError for HttpError {}
+
+/// Request that failed, if it could be parsed
+#[derive(Clone, Debug)]
+pub struct RequestContext {
+    pub id: String,
+    pub method: HttpMethod,
+    pub url: String,
+    pub ip: String,
+}
+
+/// Error passed to ErrorHandler with status and request context
+#[derive(Debug)]
+pub struct ErrorContext {
+    pub error: Error,
+    pub status: &'static str,
+    pub request: Option<RequestContext>,
+}
+
+impl ErrorContext {
+    /// Create new ErrorContext, status of HttpError or 500
+    pub fn new(error: Error, request: Option<RequestContext>) -> Self {
+        let status = error
+            .downcast_ref::<HttpError>()
+            .map(HttpError::status)
+            .unwrap_or(INTERNAL_SERVER_ERROR);
+        Self {
+            error,
+            status,
+            request,
+        }
+    }
+
+    /// Get status code
+    pub fn code(&self) -> u16 {
+        status_code(self.status)
+    }
+
+    /// Get reason phrase of status (e.g. "Bad Request")
+    pub fn reason(&self) -> &'static str {
+        self.status
+            .split_once(' ')
+            .map(|(_, reason)| reason)
+            .unwrap_or(self.status)
+    }
+
+    /// Message safe to show to clients: HttpError message for 4xx, reason phrase otherwise
+    pub fn public_message(&self) -> &str {
+        match self.error.downcast_ref::<HttpError>() {
+            Some(err) if (400..500).contains(&err.code()) => err.message(),
+            _ => self.reason(),
+        }
+    }
+}
+
+/// Parse status code of status (0 if invalid)
+fn status_code(status: &str) -> u16 {
+    status
+        .get(..3)
+        .and_then(|code| code.parse().ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,650 @@
+//! HPACK header compression (RFC 7541)
+
+use crate::{Fail, Result};
+
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+
+/// Static header table
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// Huffman code (bit length, code) for each symbol, last is EOS
+const HUFFMAN_CODES: [(u8, u32); 257] = [
+    (13, 0x1ff8),
+    (23, 0x7fffd8),
+    (28, 0xfffffe2),
+    (28, 0xfffffe3),
+    (28, 0xfffffe4),
+    (28, 0xfffffe5),
+    (28, 0xfffffe6),
+    (28, 0xfffffe7),
+    (28, 0xfffffe8),
+    (24, 0xffffea),
+    (30, 0x3ffffffc),
+    (28, 0xfffffe9),
+    (28, 0xfffffea),
+    (30, 0x3ffffffd),
+    (28, 0xfffffeb),
+    (28, 0xfffffec),
+    (28, 0xfffffed),
+    (28, 0xfffffee),
+    (28, 0xfffffef),
+    (28, 0xffffff0),
+    (28, 0xffffff1),
+    (28, 0xffffff2),
+    (30, 0x3ffffffe),
+    (28, 0xffffff3),
+    (28, 0xffffff4),
+    (28, 0xffffff5),
+    (28, 0xffffff6),
+    (28, 0xffffff7),
+    (28, 0xffffff8),
+    (28, 0xffffff9),
+    (28, 0xffffffa),
+    (28, 0xffffffb),
+    (6, 0x14),
+    (10, 0x3f8),
+    (10, 0x3f9),
+    (12, 0xffa),
+    (13, 0x1ff9),
+    (6, 0x15),
+    (8, 0xf8),
+    (11, 0x7fa),
+    (10, 0x3fa),
+    (10, 0x3fb),
+    (8, 0xf9),
+    (11, 0x7fb),
+    (8, 0xfa),
+    (6, 0x16),
+    (6, 0x17),
+    (6, 0x18),
+    (5, 0x0),
+    (5, 0x1),
+    (5, 0x2),
+    (6, 0x19),
+    (6, 0x1a),
+    (6, 0x1b),
+    (6, 0x1c),
+    (6, 0x1d),
+    (6, 0x1e),
+    (6, 0x1f),
+    (7, 0x5c),
+    (8, 0xfb),
+    (15, 0x7ffc),
+    (6, 0x20),
+    (12, 0xffb),
+    (10, 0x3fc),
+    (13, 0x1ffa),
+    (6, 0x21),
+    (7, 0x5d),
+    (7, 0x5e),
+    (7, 0x5f),
+    (7, 0x60),
+    (7, 0x61),
+    (7, 0x62),
+    (7, 0x63),
+    (7, 0x64),
+    (7, 0x65),
+    (7, 0x66),
+    (7, 0x67),
+    (7, 0x68),
+    (7, 0x69),
+    (7, 0x6a),
+    (7, 0x6b),
+    (7, 0x6c),
+    (7, 0x6d),
+    (7, 0x6e),
+    (7, 0x6f),
+    (7, 0x70),
+    (7, 0x71),
+    (7, 0x72),
+    (8, 0xfc),
+    (7, 0x73),
+    (8, 0xfd),
+    (13, 0x1ffb),
+    (19, 0x7fff0),
+    (13, 0x1ffc),
+    (14, 0x3ffc),
+    (6, 0x22),
+    (15, 0x7ffd),
+    (5, 0x3),
+    (6, 0x23),
+    (5, 0x4),
+    (6, 0x24),
+    (5, 0x5),
+    (6, 0x25),
+    (6, 0x26),
+    (6, 0x27),
+    (5, 0x6),
+    (7, 0x74),
+    (7, 0x75),
+    (6, 0x28),
+    (6, 0x29),
+    (6, 0x2a),
+    (5, 0x7),
+    (6, 0x2b),
+    (7, 0x76),
+    (6, 0x2c),
+    (5, 0x8),
+    (5, 0x9),
+    (6, 0x2d),
+    (7, 0x77),
+    (7, 0x78),
+    (7, 0x79),
+    (7, 0x7a),
+    (7, 0x7b),
+    (15, 0x7ffe),
+    (11, 0x7fc),
+    (14, 0x3ffd),
+    (13, 0x1ffd),
+    (28, 0xffffffc),
+    (20, 0xfffe6),
+    (22, 0x3fffd2),
+    (20, 0xfffe7),
+    (20, 0xfffe8),
+    (22, 0x3fffd3),
+    (22, 0x3fffd4),
+    (22, 0x3fffd5),
+    (23, 0x7fffd9),
+    (22, 0x3fffd6),
+    (23, 0x7fffda),
+    (23, 0x7fffdb),
+    (23, 0x7fffdc),
+    (23, 0x7fffdd),
+    (23, 0x7fffde),
+    (24, 0xffffeb),
+    (23, 0x7fffdf),
+    (24, 0xffffec),
+    (24, 0xffffed),
+    (22, 0x3fffd7),
+    (23, 0x7fffe0),
+    (24, 0xffffee),
+    (23, 0x7fffe1),
+    (23, 0x7fffe2),
+    (23, 0x7fffe3),
+    (23, 0x7fffe4),
+    (21, 0x1fffdc),
+    (22, 0x3fffd8),
+    (23, 0x7fffe5),
+    (22, 0x3fffd9),
+    (23, 0x7fffe6),
+    (23, 0x7fffe7),
+    (24, 0xffffef),
+    (22, 0x3fffda),
+    (21, 0x1fffdd),
+    (20, 0xfffe9),
+    (22, 0x3fffdb),
+    (22, 0x3fffdc),
+    (23, 0x7fffe8),
+    (23, 0x7fffe9),
+    (21, 0x1fffde),
+    (23, 0x7fffea),
+    (22, 0x3fffdd),
+    (22, 0x3fffde),
+    (24, 0xfffff0),
+    (21, 0x1fffdf),
+    (22, 0x3fffdf),
+    (23, 0x7fffeb),
+    (23, 0x7fffec),
+    (21, 0x1fffe0),
+    (21, 0x1fffe1),
+    (22, 0x3fffe0),
+    (21, 0x1fffe2),
+    (23, 0x7fffed),
+    (22, 0x3fffe1),
+    (23, 0x7fffee),
+    (23, 0x7fffef),
+    (20, 0xfffea),
+    (22, 0x3fffe2),
+    (22, 0x3fffe3),
+    (22, 0x3fffe4),
+    (23, 0x7ffff0),
+    (22, 0x3fffe5),
+    (22, 0x3fffe6),
+    (23, 0x7ffff1),
+    (26, 0x3ffffe0),
+    (26, 0x3ffffe1),
+    (20, 0xfffeb),
+    (19, 0x7fff1),
+    (22, 0x3fffe7),
+    (23, 0x7ffff2),
+    (22, 0x3fffe8),
+    (25, 0x1ffffec),
+    (26, 0x3ffffe2),
+    (26, 0x3ffffe3),
+    (26, 0x3ffffe4),
+    (27, 0x7ffffde),
+    (27, 0x7ffffdf),
+    (26, 0x3ffffe5),
+    (24, 0xfffff1),
+    (25, 0x1ffffed),
+    (19, 0x7fff2),
+    (21, 0x1fffe3),
+    (26, 0x3ffffe6),
+    (27, 0x7ffffe0),
+    (27, 0x7ffffe1),
+    (26, 0x3ffffe7),
+    (27, 0x7ffffe2),
+    (24, 0xfffff2),
+    (21, 0x1fffe4),
+    (21, 0x1fffe5),
+    (26, 0x3ffffe8),
+    (26, 0x3ffffe9),
+    (28, 0xffffffd),
+    (27, 0x7ffffe3),
+    (27, 0x7ffffe4),
+    (27, 0x7ffffe5),
+    (20, 0xfffec),
+    (24, 0xfffff3),
+    (20, 0xfffed),
+    (21, 0x1fffe6),
+    (22, 0x3fffe9),
+    (21, 0x1fffe7),
+    (21, 0x1fffe8),
+    (23, 0x7ffff3),
+    (22, 0x3fffea),
+    (22, 0x3fffeb),
+    (25, 0x1ffffee),
+    (25, 0x1ffffef),
+    (24, 0xfffff4),
+    (24, 0xfffff5),
+    (26, 0x3ffffea),
+    (23, 0x7ffff4),
+    (26, 0x3ffffeb),
+    (27, 0x7ffffe6),
+    (26, 0x3ffffec),
+    (26, 0x3ffffed),
+    (27, 0x7ffffe7),
+    (27, 0x7ffffe8),
+    (27, 0x7ffffe9),
+    (27, 0x7ffffea),
+    (27, 0x7ffffeb),
+    (28, 0xffffffe),
+    (27, 0x7ffffec),
+    (27, 0x7ffffed),
+    (27, 0x7ffffee),
+    (27, 0x7ffffef),
+    (27, 0x7fffff0),
+    (26, 0x3ffffee),
+    (30, 0x3fffffff),
+];
+
+/// Default maximum dynamic table size
+pub const DEFAULT_TABLE_SIZE: usize = 4096;
+
+/// HPACK decoder with dynamic table
+#[derive(Debug)]
+pub struct Decoder {
+    table: VecDeque<(String, String)>,
+    size: usize,
+    max_size: usize,
+}
+
+impl Decoder {
+    /// Create new decoder with default table size
+    pub fn new() -> Self {
+        Self {
+            table: VecDeque::new(),
+            size: 0,
+            max_size: DEFAULT_TABLE_SIZE,
+        }
+    }
+
+    /// Decode header block to list of (name, value)<br>
+    /// Returns None if the header list is larger than max_list_size (RFC 9113 6.5.2),
+    /// the rest of the block is still processed to keep the dynamic table in sync
+    pub fn decode(
+        &mut self,
+        mut block: &[u8],
+        max_list_size: usize,
+    ) -> Result<Option<Vec<(String, String)>>> {
+        let mut headers = Vec::new();
+        let mut list_size = 0;
+        while let Some(&first) = block.first() {
+            if first & 0x80 != 0 {
+                // indexed header field, only copied if the list isn't too large
+                let (index, rest) = decode_integer(block, 7)?;
+                let (name, value) = self.get(index)?;
+                list_size += name.len() + value.len() + 32;
+                if list_size <= max_list_size {
+                    headers.push((name.to_string(), value.to_string()));
+                }
+                block = rest;
+            } else if first & 0x40 != 0 {
+                // literal header field with incremental indexing
+                let (header, rest) = self.decode_literal(block, 6)?;
+                list_size += header.0.len() + header.1.len() + 32;
+                if list_size <= max_list_size {
+                    headers.push(header.clone());
+                }
+                self.insert(header);
+                block = rest;
+            } else if first & 0x20 != 0 {
+                // dynamic table size update
+                let (max_size, rest) = decode_integer(block, 5)?;
+                if max_size > DEFAULT_TABLE_SIZE {
+                    return Fail::from("HPACK table size update too large");
+                }
+                self.max_size = max_size;
+                self.evict(0);
+                block = rest;
+            } else {
+                // literal header field without indexing or never indexed
+                let (header, rest) = self.decode_literal(block, 4)?;
+                list_size += header.0.len() + header.1.len() + 32;
+                if list_size <= max_list_size {
+                    headers.push(header);
+                }
+                block = rest;
+            }
+        }
+        Ok((list_size <= max_list_size).then_some(headers))
+    }
+
+    /// Get header from static or dynamic table
+    fn get(&self, index: usize) -> Result<(&str, &str)> {
+        match index {
+            0 => Fail::from("HPACK index 0"),
+            1..=61 => Ok(STATIC_TABLE[index - 1]),
+            _ => match self.table.get(index - 62) {
+                Some((name, value)) => Ok((name, value)),
+                None => Fail::from("HPACK index out of range"),
+            },
+        }
+    }
+
+    /// Decode literal header field with name index of prefix bits
+    fn decode_literal<'a>(
+        &self,
+        block: &'a [u8],
+        prefix: u8,
+    ) -> Result<((String, String), &'a [u8])> {
+        let (index, rest) = decode_integer(block, prefix)?;
+        let (name, rest) = match index {
+            0 => decode_string(rest)?,
+            index => (self.get(index)?.0.to_string(), rest),
+        };
+        let (value, rest) = decode_string(rest)?;
+        Ok(((name, value), rest))
+    }
+
+    /// Insert header into dynamic table
+    fn insert(&mut self, header: (String, String)) {
+        let size = header.0.len() + header.1.len() + 32;
+        self.evict(size);
+        if size <= self.max_size {
+            self.size += size;
+            self.table.push_front(header);
+        }
+    }
+
+    /// Evict entries until additional bytes fit into dynamic table
+    fn evict(&mut self, additional: usize) {
+        while self.size + additional > self.max_size {
+            match self.table.pop_back() {
+                Some((name, value)) => self.size -= name.len() + value.len() + 32,
+                None => break,
+            }
+        }
+    }
+}
+
+/// Encode headers without using the dynamic table
+pub fn encode(headers: &[(&str, &str)]) -> Vec<u8> {
+    let mut block = Vec::new();
+    for &(name, value) in headers {
+        if let Some(index) = STATIC_TABLE.iter().position(|&h| h == (name, value)) {
+            // indexed header field
+            encode_integer(&mut block, 0x80, 7, index + 1);
+        } else if let Some(index) = STATIC_TABLE.iter().position(|&(n, _)| n == name) {
+            // literal without indexing, indexed name
+            encode_integer(&mut block, 0x00, 4, index + 1);
+            encode_string(&mut block, value);
+        } else {
+            // literal without indexing, new name
+            block.push(0x00);
+            encode_string(&mut block, name);
+            encode_string(&mut block, value);
+        }
+    }
+    block
+}
+
+/// Decode integer with prefix bits
+fn decode_integer(data: &[u8], prefix: u8) -> Result<(usize, &[u8])> {
+    let max = (1usize << prefix) - 1;
+    let (&first, mut rest) = data
+        .split_first()
+        .ok_or_else(|| Fail::new("HPACK integer truncated"))?;
+    let mut value = (first as usize) & max;
+    if value < max {
+        return Ok((value, rest));
+    }
+
+    // continuation bytes
+    let mut shift = 0;
+    loop {
+        let (&b, next) = rest
+            .split_first()
+            .ok_or_else(|| Fail::new("HPACK integer truncated"))?;
+        rest = next;
+        if shift > 28 {
+            return Fail::from("HPACK integer overflow");
+        }
+        value += ((b & 0x7f) as usize) << shift;
+        shift += 7;
+        if b & 0x80 == 0 {
+            return Ok((value, rest));
+        }
+    }
+}
+
+/// Encode integer with prefix bits and first byte flags
+fn encode_integer(block: &mut Vec<u8>, flags: u8, prefix: u8, mut value: usize) {
+    let max = (1usize << prefix) - 1;
+    if value < max {
+        block.push(flags | value as u8);
+        return;
+    }
+    block.push(flags | max as u8);
+    value -= max;
+    while value >= 0x80 {
+        block.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    block.push(value as u8);
+}
+
+/// Decode string literal (optionally huffman encoded)
+fn decode_string(data: &[u8]) -> Result<(String, &[u8])> {
+    let huffman = data.first().is_some_and(|&b| b & 0x80 != 0);
+    let (len, rest) = decode_integer(data, 7)?;
+    if rest.len() < len {
+        return Fail::from("HPACK string truncated");
+    }
+    let (raw, rest) = rest.split_at(len);
+    let raw = if huffman {
+        huffman_decode(raw)?
+    } else {
+        raw.to_vec()
+    };
+    Ok((String::from_utf8(raw)?, rest))
+}
+
+/// Encode string literal (without huffman encoding)
+fn encode_string(block: &mut Vec<u8>, value: &str) {
+    encode_integer(block, 0x00, 7, value.len());
+    block.extend_from_slice(value.as_bytes());
+}
+
+/// Node of huffman decoding tree
+#[derive(Clone, Copy)]
+enum HuffmanNode {
+    Branch(usize),
+    Symbol(usize),
+}
+
+/// Huffman decoding tree, each node has two children
+fn huffman_tree() -> &'static Vec<[Option<HuffmanNode>; 2]> {
+    static TREE: OnceLock<Vec<[Option<HuffmanNode>; 2]>> = OnceLock::new();
+    TREE.get_or_init(|| {
+        let mut tree = vec![[None, None]];
+        for (symbol, &(bits, code)) in HUFFMAN_CODES.iter().enumerate() {
+            let mut node = 0;
+            for i in (0..bits).rev() {
+                let bit = ((code >> i) & 1) as usize;
+                if i == 0 {
+                    tree[node][bit] = Some(HuffmanNode::Symbol(symbol));
+                } else if let Some(HuffmanNode::Branch(next)) = tree[node][bit] {
+                    node = next;
+                } else {
+                    tree.push([None, None]);
+                    let next = tree.len() - 1;
+                    tree[node][bit] = Some(HuffmanNode::Branch(next));
+                    node = next;
+                }
+            }
+        }
+        tree
+    })
+}
+
+/// Decode huffman encoded string
+fn huffman_decode(data: &[u8]) -> Result<Vec<u8>> {
+    let tree = huffman_tree();
+    let mut decoded = Vec::with_capacity(data.len() * 8 / 5);
+    let mut node = 0;
+    let mut depth = 0;
+    let mut padding_ones = true;
+    for &b in data {
+        for i in (0..8).rev() {
+            let bit = ((b >> i) & 1) as usize;
+            padding_ones &= bit == 1;
+            depth += 1;
+            match tree[node][bit] {
+                Some(HuffmanNode::Symbol(256)) => {
+                    return Fail::from("HPACK huffman EOS decoded");
+                }
+                Some(HuffmanNode::Symbol(symbol)) => {
+                    decoded.push(symbol as u8);
+                    node = 0;
+                    depth = 0;
+                    padding_ones = true;
+                }
+                Some(HuffmanNode::Branch(next)) => node = next,
+                None => return Fail::from("HPACK huffman invalid code"),
+            }
+        }
+    }
+
+    // padding must be shorter than 8 bits and consist of ones
+    if depth > 7 || !padding_ones {
+        return Fail::from("HPACK huffman invalid padding");
+    }
+    Ok(decoded)
+}
+
+#[test]
+fn test_hpack() {
+    // RFC 7541 C.4.1 request with huffman encoding
+    let mut decoder = Decoder::new();
+    let block = [
+        0x82, 0x86, 0x84, 0x41, 0x8c, 0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90,
+        0xf4, 0xff,
+    ];
+    let headers = decoder.decode(&block, 8192).unwrap().unwrap();
+    assert_eq!(
+        headers,
+        [
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":path", "/"),
+            (":authority", "www.example.com"),
+        ]
+        .map(|(n, v)| (n.to_string(), v.to_string()))
+    );
+
+    // RFC 7541 C.4.2 uses dynamic table entry of first request
+    let block = [
+        0x82, 0x86, 0x84, 0xbe, 0x58, 0x86, 0xa8, 0xeb, 0x10, 0x64, 0x9c, 0xbf,
+    ];
+    let headers = decoder.decode(&block, 8192).unwrap().unwrap();
+    assert_eq!(headers[3].1, "www.example.com");
+    assert_eq!(headers[4], ("cache-control".into(), "no-cache".into()));
+
+    // header list larger than limit (each entry counts 32 bytes overhead)
+    assert_eq!(decoder.decode(&block, 150).unwrap(), None);
+
+    // encode and decode again
+    let encoded = encode(&[
+        (":status", "200"),
+        ("content-type", "text/plain"),
+        ("x-a", "b"),
+    ]);
+    let headers = decoder.decode(&encoded, 8192).unwrap().unwrap();
+    assert_eq!(headers[0], (":status".into(), "200".into()));
+    assert_eq!(headers[1], ("content-type".into(), "text/plain".into()));
+    assert_eq!(headers[2], ("x-a".into(), "b".into()));
+}
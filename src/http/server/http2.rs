@@ -0,0 +1,721 @@
+//! HTTP/2 connection handling (RFC 9113)
+
+use std::collections::BTreeMap;
+use std::error::Error as StdError;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io::{ErrorKind, empty};
+
+use crate::http::common::ReadWrite;
+use crate::{Fail, Result};
+
+use super::hpack::{Decoder, encode};
+use super::server::handle_request;
+use super::{ErrorContext, HttpError, HttpServer, PeerAddr, split_response};
+
+#[cfg(feature = "tls")]
+use super::ClientCertificate;
+
+/// Client connection preface
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+// frame types
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const PRIORITY: u8 = 0x2;
+const RST_STREAM: u8 = 0x3;
+const SETTINGS: u8 = 0x4;
+const PUSH_PROMISE: u8 = 0x5;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const WINDOW_UPDATE: u8 = 0x8;
+const CONTINUATION: u8 = 0x9;
+
+// frame flags
+const FLAG_END_STREAM: u8 = 0x1;
+const FLAG_ACK: u8 = 0x1;
+const FLAG_END_HEADERS: u8 = 0x4;
+const FLAG_PADDED: u8 = 0x8;
+const FLAG_PRIORITY: u8 = 0x20;
+
+// settings
+const SETTINGS_ENABLE_PUSH: u16 = 0x2;
+const SETTINGS_MAX_CONCURRENT_STREAMS: u16 = 0x3;
+const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x4;
+const SETTINGS_MAX_FRAME_SIZE: u16 = 0x5;
+const SETTINGS_MAX_HEADER_LIST_SIZE: u16 = 0x6;
+
+// error codes
+const NO_ERROR: u32 = 0x0;
+const PROTOCOL_ERROR: u32 = 0x1;
+const FLOW_CONTROL_ERROR: u32 = 0x3;
+const FRAME_SIZE_ERROR: u32 = 0x6;
+const REFUSED_STREAM: u32 = 0x7;
+const COMPRESSION_ERROR: u32 = 0x9;
+const ENHANCE_YOUR_CALM: u32 = 0xb;
+
+/// Default flow control window and maximum frame size
+const DEFAULT_WINDOW: i64 = 65_535;
+const MAX_WINDOW: i64 = 0x7fff_ffff;
+const DEFAULT_MAX_FRAME_SIZE: usize = 16_384;
+
+/// Maximum number of concurrent streams per connection
+const MAX_CONCURRENT_STREAMS: usize = 100;
+
+/// Headers not allowed in HTTP/2 responses
+const CONNECTION_HEADERS: [&str; 5] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// Connection error with HTTP/2 error code
+#[derive(Debug)]
+struct ConnectionError(u32, &'static str);
+
+impl StdError for ConnectionError {}
+
+impl Display for ConnectionError {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        write!(formatter, "HTTP/2 connection error: {}", self.1)
+    }
+}
+
+/// Create Result with ConnectionError
+fn connection_error<T>(code: u32, message: &'static str) -> Result<T> {
+    Err(Box::new(ConnectionError(code, message)))
+}
+
+/// Stream state
+#[derive(Debug, Default)]
+struct Stream {
+    header_block: Vec<u8>,
+    headers: Option<Vec<(String, String)>>,
+    body: Vec<u8>,
+    end_stream: bool,
+    refused: bool,
+    window: i64,
+    pending: Option<Vec<u8>>,
+}
+
+/// HTTP/2 connection serving requests using the server's handler
+pub struct Http2Connection<'a, S: ReadWrite> {
+    stream: &'a mut S,
+    server: &'a HttpServer,
+    address: PeerAddr,
+    buffered: Vec<u8>,
+    decoder: Decoder,
+    streams: BTreeMap<u32, Stream>,
+    last_stream_id: u32,
+    continuation: Option<u32>,
+    send_window: i64,
+    initial_window: i64,
+    max_frame_size: usize,
+    #[cfg(feature = "tls")]
+    client_certificate: Option<ClientCertificate>,
+}
+
+impl<'a, S: ReadWrite> Http2Connection<'a, S> {
+    /// Create new connection, buffered contains already read bytes (e.g. h2c preface)
+    pub fn new(
+        stream: &'a mut S,
+        server: &'a HttpServer,
+        address: PeerAddr,
+        buffered: Vec<u8>,
+        #[cfg(feature = "tls")] client_certificate: Option<ClientCertificate>,
+    ) -> Self {
+        Self {
+            stream,
+            server,
+            address,
+            buffered,
+            decoder: Decoder::new(),
+            streams: BTreeMap::new(),
+            last_stream_id: 0,
+            continuation: None,
+            send_window: DEFAULT_WINDOW,
+            initial_window: DEFAULT_WINDOW,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            #[cfg(feature = "tls")]
+            client_certificate,
+        }
+    }
+
+    /// Serve requests until the connection is closed
+    pub fn serve(mut self) -> Result<()> {
+        // check client connection preface
+        let mut preface = [0u8; PREFACE.len()];
+        self.read_exact(&mut preface)?;
+        if preface != PREFACE {
+            return Fail::from("Invalid HTTP/2 connection preface");
+        }
+
+        // send server connection preface
+        let mut settings = Vec::new();
+        push_setting(&mut settings, SETTINGS_ENABLE_PUSH, 0);
+        push_setting(
+            &mut settings,
+            SETTINGS_MAX_CONCURRENT_STREAMS,
+            MAX_CONCURRENT_STREAMS as u32,
+        );
+        push_setting(
+            &mut settings,
+            SETTINGS_MAX_HEADER_LIST_SIZE,
+            self.server.settings().max_header_size as u32,
+        );
+        self.write_frame(SETTINGS, 0, 0, &settings)?;
+
+        // process frames
+        loop {
+            let (kind, flags, id, payload) = match self.read_frame() {
+                Ok(frame) => frame,
+                Err(err) => {
+                    return match err.downcast_ref::<std::io::Error>() {
+                        Some(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(()),
+                        _ => Err(err),
+                    };
+                }
+            };
+            if let Err(err) = self.process_frame(kind, flags, id, &payload) {
+                let code = match err.downcast_ref::<ConnectionError>() {
+                    Some(ConnectionError(code, _)) => *code,
+                    None => PROTOCOL_ERROR,
+                };
+                self.goaway(code).ok();
+                return Err(err);
+            }
+        }
+    }
+
+    /// Process a single frame
+    fn process_frame(&mut self, kind: u8, flags: u8, id: u32, payload: &[u8]) -> Result<()> {
+        // header block must be continued
+        if let Some(expected) = self.continuation
+            && (kind != CONTINUATION || id != expected)
+        {
+            return connection_error(PROTOCOL_ERROR, "expected CONTINUATION frame");
+        }
+
+        match kind {
+            DATA => self.on_data(flags, id, payload),
+            HEADERS => self.on_headers(flags, id, payload),
+            CONTINUATION => {
+                if self.continuation != Some(id) {
+                    return connection_error(PROTOCOL_ERROR, "unexpected CONTINUATION frame");
+                }
+                self.append_header_block(id, payload)?;
+                if flags & FLAG_END_HEADERS != 0 {
+                    self.continuation = None;
+                    self.end_headers(id)?;
+                }
+                Ok(())
+            }
+            PRIORITY => Ok(()),
+            RST_STREAM => {
+                if id == 0 || payload.len() != 4 {
+                    return connection_error(PROTOCOL_ERROR, "invalid RST_STREAM frame");
+                }
+                self.streams.remove(&id);
+                Ok(())
+            }
+            SETTINGS => self.on_settings(flags, id, payload),
+            PUSH_PROMISE => connection_error(PROTOCOL_ERROR, "PUSH_PROMISE from client"),
+            PING => {
+                if id != 0 || payload.len() != 8 {
+                    return connection_error(FRAME_SIZE_ERROR, "invalid PING frame");
+                }
+                if flags & FLAG_ACK == 0 {
+                    self.write_frame(PING, FLAG_ACK, 0, payload)?;
+                }
+                Ok(())
+            }
+            GOAWAY => Ok(()),
+            WINDOW_UPDATE => self.on_window_update(id, payload),
+            _ => Ok(()),
+        }
+    }
+
+    /// Process DATA frame
+    fn on_data(&mut self, flags: u8, id: u32, payload: &[u8]) -> Result<()> {
+        if id == 0 {
+            return connection_error(PROTOCOL_ERROR, "DATA frame on stream 0");
+        } else if id > self.last_stream_id {
+            return connection_error(PROTOCOL_ERROR, "DATA frame on idle stream");
+        }
+        let data = strip_padding(flags, payload)?;
+
+        // replenish connection window
+        if !payload.is_empty() {
+            self.write_window_update(0, payload.len())?;
+        }
+
+        // ignore data for closed streams
+        let max_body_size = self.server.settings().max_body_size;
+        let stream = match self.streams.get_mut(&id) {
+            Some(stream) if stream.headers.is_some() && !stream.end_stream => stream,
+            _ => return Ok(()),
+        };
+
+        // check body size
+        stream.body.extend_from_slice(data);
+        if stream.body.len() > max_body_size {
+            stream.end_stream = true;
+            stream.body.clear();
+            let response = (self.server.error_handler)(ErrorContext::new(
+                HttpError::content_too_large("Max body size exceeded"),
+                None,
+            ));
+            self.send_response(id, response)?;
+            return self.write_frame(RST_STREAM, 0, id, &NO_ERROR.to_be_bytes());
+        }
+
+        // dispatch or replenish stream window
+        if flags & FLAG_END_STREAM != 0 {
+            stream.end_stream = true;
+            self.dispatch(id)
+        } else if !payload.is_empty() {
+            self.write_window_update(id, payload.len())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Process HEADERS frame
+    fn on_headers(&mut self, flags: u8, id: u32, payload: &[u8]) -> Result<()> {
+        if id == 0 {
+            return connection_error(PROTOCOL_ERROR, "HEADERS frame on stream 0");
+        }
+        let mut fragment = strip_padding(flags, payload)?;
+        if flags & FLAG_PRIORITY != 0 {
+            fragment = fragment
+                .get(5..)
+                .ok_or(ConnectionError(FRAME_SIZE_ERROR, "HEADERS frame too short"))?;
+        }
+
+        // open new stream
+        if !self.streams.contains_key(&id) {
+            if id.is_multiple_of(2) || id <= self.last_stream_id {
+                return connection_error(PROTOCOL_ERROR, "invalid stream identifier");
+            }
+            self.last_stream_id = id;
+            let refused = self.streams.len() >= MAX_CONCURRENT_STREAMS;
+            self.streams.insert(
+                id,
+                Stream {
+                    refused,
+                    window: self.initial_window,
+                    ..Default::default()
+                },
+            );
+        }
+
+        // collect header block
+        self.append_header_block(id, fragment)?;
+        if let Some(stream) = self.streams.get_mut(&id) {
+            stream.end_stream |= flags & FLAG_END_STREAM != 0;
+        }
+        if flags & FLAG_END_HEADERS != 0 {
+            self.end_headers(id)
+        } else {
+            self.continuation = Some(id);
+            Ok(())
+        }
+    }
+
+    /// Append header block fragment to stream
+    fn append_header_block(&mut self, id: u32, fragment: &[u8]) -> Result<()> {
+        let max_header_size = self.server.settings().max_header_size;
+        let stream = self.streams.get_mut(&id).ok_or(ConnectionError(
+            PROTOCOL_ERROR,
+            "header block for closed stream",
+        ))?;
+        stream.header_block.extend_from_slice(fragment);
+        if stream.header_block.len() > max_header_size * 4 {
+            return connection_error(ENHANCE_YOUR_CALM, "header block too large");
+        }
+        Ok(())
+    }
+
+    /// Decode complete header block and dispatch if stream ended
+    fn end_headers(&mut self, id: u32) -> Result<()> {
+        let stream = match self.streams.get_mut(&id) {
+            Some(stream) => stream,
+            None => return Ok(()),
+        };
+
+        // decode (trailers are decoded to keep HPACK state, but ignored)
+        let block = std::mem::take(&mut stream.header_block);
+        let headers = self
+            .decoder
+            .decode(&block, self.server.settings().max_header_size)
+            .or_else(|_| connection_error(COMPRESSION_ERROR, "HPACK decoding failed"))?;
+        let too_large = headers.is_none() && stream.headers.is_none();
+        if stream.headers.is_none() {
+            stream.headers = headers;
+        }
+
+        // refuse stream if too many are open
+        if stream.refused {
+            self.streams.remove(&id);
+            return self.write_frame(RST_STREAM, 0, id, &REFUSED_STREAM.to_be_bytes());
+        }
+
+        // reject header list larger than advertised
+        if too_large {
+            let response = (self.server.error_handler)(ErrorContext::new(
+                HttpError::header_too_large("Max header size exceeded"),
+                None,
+            ));
+            return self.send_response(id, response);
+        }
+
+        if stream.end_stream {
+            self.dispatch(id)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Process SETTINGS frame
+    fn on_settings(&mut self, flags: u8, id: u32, payload: &[u8]) -> Result<()> {
+        if id != 0 {
+            return connection_error(PROTOCOL_ERROR, "SETTINGS frame on stream");
+        } else if flags & FLAG_ACK != 0 {
+            return match payload.is_empty() {
+                true => Ok(()),
+                false => connection_error(FRAME_SIZE_ERROR, "SETTINGS ACK with payload"),
+            };
+        } else if !payload.len().is_multiple_of(6) {
+            return connection_error(FRAME_SIZE_ERROR, "invalid SETTINGS frame");
+        }
+
+        // apply settings
+        for setting in payload.chunks_exact(6) {
+            let key = u16::from_be_bytes([setting[0], setting[1]]);
+            let value = u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]]);
+            match key {
+                SETTINGS_INITIAL_WINDOW_SIZE => {
+                    if value as i64 > MAX_WINDOW {
+                        return connection_error(FLOW_CONTROL_ERROR, "initial window too large");
+                    }
+                    let delta = value as i64 - self.initial_window;
+                    self.initial_window = value as i64;
+                    for stream in self.streams.values_mut() {
+                        stream.window += delta;
+                        if stream.window > MAX_WINDOW {
+                            return connection_error(FLOW_CONTROL_ERROR, "stream window too large");
+                        }
+                    }
+                }
+                SETTINGS_MAX_FRAME_SIZE => {
+                    if !(16_384..=16_777_215).contains(&value) {
+                        return connection_error(PROTOCOL_ERROR, "invalid max frame size");
+                    }
+                    self.max_frame_size = value as usize;
+                }
+                _ => {}
+            }
+        }
+
+        // acknowledge and send data if window grew
+        self.write_frame(SETTINGS, FLAG_ACK, 0, &[])?;
+        self.flush_all()
+    }
+
+    /// Process WINDOW_UPDATE frame
+    fn on_window_update(&mut self, id: u32, payload: &[u8]) -> Result<()> {
+        let increment = match payload {
+            [a, b, c, d] => (u32::from_be_bytes([*a, *b, *c, *d]) & 0x7fff_ffff) as i64,
+            _ => return connection_error(FRAME_SIZE_ERROR, "invalid WINDOW_UPDATE frame"),
+        };
+        if increment == 0 {
+            return connection_error(PROTOCOL_ERROR, "WINDOW_UPDATE with zero increment");
+        }
+
+        // increase connection or stream window
+        if id == 0 {
+            self.send_window += increment;
+            if self.send_window > MAX_WINDOW {
+                return connection_error(FLOW_CONTROL_ERROR, "connection window too large");
+            }
+            self.flush_all()
+        } else if let Some(stream) = self.streams.get_mut(&id) {
+            stream.window += increment;
+            if stream.window > MAX_WINDOW {
+                // stream error, connection stays usable
+                self.streams.remove(&id);
+                return self.write_frame(RST_STREAM, 0, id, &FLOW_CONTROL_ERROR.to_be_bytes());
+            }
+            self.flush_stream(id)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Pass complete request to handler and send response
+    fn dispatch(&mut self, id: u32) -> Result<()> {
+        let stream = match self.streams.get_mut(&id) {
+            Some(stream) => stream,
+            None => return Ok(()),
+        };
+        let headers = stream.headers.take().unwrap_or_default();
+        let body = std::mem::take(&mut stream.body);
+
+        // malformed request, fields must not break the HTTP/1.1 style header
+        if !headers.iter().all(|(name, value)| valid_field(name, value)) {
+            self.streams.remove(&id);
+            return self.write_frame(RST_STREAM, 0, id, &PROTOCOL_ERROR.to_be_bytes());
+        }
+
+        // convert to HTTP/1.1 style header
+        let mut method = "";
+        let mut path = "/";
+        let (mut scheme, mut authority) = (None, None);
+        let mut raw_header = String::new();
+        let mut cookies = Vec::new();
+        for (name, value) in &headers {
+            match name.as_str() {
+                ":method" => method = value,
+                ":path" => path = value,
+                ":scheme" => scheme = Some(value),
+                ":authority" => {
+                    authority = Some(value);
+                    raw_header.push_str(&format!("host: {value}\r\n"));
+                }
+                "cookie" => cookies.push(value.as_str()),
+                "content-length" | "host" => {}
+                name if name.starts_with(':') => {}
+                name => raw_header.push_str(&format!("{name}: {value}\r\n")),
+            }
+        }
+        if !cookies.is_empty() {
+            raw_header.push_str(&format!("cookie: {}\r\n", cookies.join("; ")));
+        }
+        // absolute-form to keep scheme of request
+        let target = match (scheme, authority) {
+            (Some(scheme), Some(authority)) if path.starts_with('/') => {
+                format!("{scheme}://{authority}{path}")
+            }
+            _ => path.to_string(),
+        };
+        let raw_header = format!(
+            "{method} {target} HTTP/2\r\n{raw_header}content-length: {}\r\n\r\n",
+            body.len()
+        );
+
+        // handle request
+        let response = if raw_header.len() > self.server.settings().max_header_size {
+            (self.server.error_handler)(ErrorContext::new(
+                HttpError::header_too_large("Max header size exceeded"),
+                None,
+            ))
+        } else {
+            handle_request(
+                self.server,
+                &raw_header,
+                body,
+                &mut empty(),
+                self.address.clone(),
+                false,
+                #[cfg(feature = "tls")]
+                self.client_certificate.clone(),
+            )
+        };
+        self.send_response(id, response)
+    }
+
+    /// Send HTTP/1.1 style response as HEADERS and DATA frames
+    fn send_response(&mut self, id: u32, response: Vec<u8>) -> Result<()> {
+        let (status, headers, body) =
+            split_response(&response).unwrap_or(("500 Internal Server Error", Vec::new(), &[]));
+
+        // encode headers
+        let code = status.split(' ').next().unwrap_or("500");
+        let headers: Vec<(String, &str)> = headers
+            .into_iter()
+            .map(|(name, value)| (name.to_lowercase(), value))
+            .filter(|(name, _)| !CONNECTION_HEADERS.contains(&name.as_str()))
+            .collect();
+        let mut list = vec![(":status", code)];
+        list.extend(headers.iter().map(|(name, value)| (name.as_str(), *value)));
+        let block = encode(&list);
+
+        // send header block
+        let end_stream = if body.is_empty() { FLAG_END_STREAM } else { 0 };
+        let mut fragments = block.chunks(self.max_frame_size).peekable();
+        let mut kind = HEADERS;
+        while let Some(fragment) = fragments.next() {
+            let end_headers = if fragments.peek().is_none() {
+                FLAG_END_HEADERS
+            } else {
+                0
+            };
+            let flags = if kind == HEADERS { end_stream } else { 0 } | end_headers;
+            self.write_frame(kind, flags, id, fragment)?;
+            kind = CONTINUATION;
+        }
+        if block.is_empty() {
+            self.write_frame(HEADERS, end_stream | FLAG_END_HEADERS, id, &[])?;
+        }
+
+        // send body
+        if body.is_empty() {
+            self.close_stream(id)
+        } else {
+            if let Some(stream) = self.streams.get_mut(&id) {
+                stream.pending = Some(body.to_vec());
+            }
+            self.flush_stream(id)
+        }
+    }
+
+    /// Send pending data of all streams
+    fn flush_all(&mut self) -> Result<()> {
+        let ids: Vec<u32> = self
+            .streams
+            .iter()
+            .filter(|(_, stream)| stream.pending.is_some())
+            .map(|(&id, _)| id)
+            .collect();
+        for id in ids {
+            self.flush_stream(id)?;
+        }
+        Ok(())
+    }
+
+    /// Send pending data of stream as far as flow control allows
+    fn flush_stream(&mut self, id: u32) -> Result<()> {
+        loop {
+            let stream = match self.streams.get_mut(&id) {
+                Some(stream) => stream,
+                None => return Ok(()),
+            };
+            let pending = match stream.pending.as_mut() {
+                Some(pending) => pending,
+                None => return Ok(()),
+            };
+
+            // check windows
+            let len = (pending.len() as i64)
+                .min(self.max_frame_size as i64)
+                .min(self.send_window)
+                .min(stream.window);
+            if len <= 0 {
+                return Ok(());
+            }
+            stream.window -= len;
+            self.send_window -= len;
+
+            // send data frame
+            let data: Vec<u8> = pending.drain(..len as usize).collect();
+            let end_stream = pending.is_empty();
+            let flags = if end_stream { FLAG_END_STREAM } else { 0 };
+            self.write_frame(DATA, flags, id, &data)?;
+            if end_stream {
+                return self.close_stream(id);
+            }
+        }
+    }
+
+    /// Remove stream after response was sent
+    fn close_stream(&mut self, id: u32) -> Result<()> {
+        if let Some(stream) = self.streams.remove(&id)
+            && !stream.end_stream
+        {
+            // tell client to stop sending
+            self.write_frame(RST_STREAM, 0, id, &NO_ERROR.to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Send WINDOW_UPDATE frame
+    fn write_window_update(&mut self, id: u32, increment: usize) -> Result<()> {
+        self.write_frame(WINDOW_UPDATE, 0, id, &(increment as u32).to_be_bytes())
+    }
+
+    /// Send GOAWAY frame
+    fn goaway(&mut self, code: u32) -> Result<()> {
+        let mut payload = self.last_stream_id.to_be_bytes().to_vec();
+        payload.extend_from_slice(&code.to_be_bytes());
+        self.write_frame(GOAWAY, 0, 0, &payload)
+    }
+
+    /// Write frame to stream
+    fn write_frame(&mut self, kind: u8, flags: u8, id: u32, payload: &[u8]) -> Result<()> {
+        let len = (payload.len() as u32).to_be_bytes();
+        let mut frame = Vec::with_capacity(9 + payload.len());
+        frame.extend_from_slice(&len[1..]);
+        frame.push(kind);
+        frame.push(flags);
+        frame.extend_from_slice(&(id & 0x7fff_ffff).to_be_bytes());
+        frame.extend_from_slice(payload);
+        self.stream.write_all(&frame)?;
+        self.stream.flush().or_else(Fail::from)
+    }
+
+    /// Read frame (type, flags, stream identifier, payload)
+    fn read_frame(&mut self) -> Result<(u8, u8, u32, Vec<u8>)> {
+        let mut header = [0u8; 9];
+        self.read_exact(&mut header)?;
+        let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        if len > DEFAULT_MAX_FRAME_SIZE {
+            return connection_error(FRAME_SIZE_ERROR, "frame too large");
+        }
+        let id = u32::from_be_bytes([header[5], header[6], header[7], header[8]]) & 0x7fff_ffff;
+        let mut payload = vec![0u8; len];
+        self.read_exact(&mut payload)?;
+        Ok((header[3], header[4], id, payload))
+    }
+
+    /// Read exactly buf.len() bytes, using buffered bytes first
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+        let buffered = buf.len().min(self.buffered.len());
+        buf[..buffered].copy_from_slice(&self.buffered[..buffered]);
+        self.buffered.drain(..buffered);
+        self.stream.read_exact(&mut buf[buffered..])?;
+        Ok(())
+    }
+}
+
+/// Remove padding of DATA or HEADERS frame payload
+fn strip_padding(flags: u8, payload: &[u8]) -> Result<&[u8]> {
+    if flags & FLAG_PADDED == 0 {
+        return Ok(payload);
+    }
+    match payload.split_first() {
+        Some((&padding, rest)) if (padding as usize) <= rest.len() => {
+            Ok(&rest[..rest.len() - padding as usize])
+        }
+        _ => connection_error(PROTOCOL_ERROR, "invalid padding"),
+    }
+}
+
+/// Check whether field is well-formed (RFC 9113 8.2.1 and 8.3.1)
+fn valid_field(name: &str, value: &str) -> bool {
+    let field_name = name.strip_prefix(':').unwrap_or(name);
+    !field_name.is_empty()
+        && field_name
+            .bytes()
+            .all(|b| b.is_ascii_graphic() && !b.is_ascii_uppercase() && b != b':')
+        && !value.bytes().any(|b| matches!(b, b'\r' | b'\n' | b'\0'))
+        && match name {
+            ":method" => is_token(value),
+            ":path" | ":authority" | ":scheme" => !value.bytes().any(|b| b.is_ascii_whitespace()),
+            _ => true,
+        }
+}
+
+/// Check whether value is a token (RFC 9110 5.6.2)
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Add setting to SETTINGS payload
+fn push_setting(payload: &mut Vec<u8>, key: u16, value: u32) {
+    payload.extend_from_slice(&key.to_be_bytes());
+    payload.extend_from_slice(&value.to_be_bytes());
+}
@@ -0,0 +1,340 @@
+//! Listeners for incoming connections
+
+use std::io::{Read, Result as IoResult, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::Result;
+
+#[cfg(unix)]
+use {
+    crate::Fail,
+    libc::{F_GETFD, F_SETFD, FD_CLOEXEC, SO_TYPE, SOCK_STREAM, SOL_SOCKET, c_int, socklen_t},
+    std::env::var,
+    std::fs::{Permissions, remove_file, set_permissions, symlink_metadata},
+    std::io::{Error as IoError, ErrorKind},
+    std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    std::os::unix::fs::{FileTypeExt, PermissionsExt},
+    std::os::unix::net::{UnixListener, UnixStream},
+    std::path::{Path, PathBuf},
+    std::process,
+    std::sync::atomic::{AtomicBool, Ordering},
+};
+
+#[cfg(feature = "tls")]
+use {
+    super::{TlsConfig, TlsConfigProvider},
+    std::sync::{Arc, Mutex},
+};
+
+/// First file descriptor passed by systemd socket activation
+#[cfg(unix)]
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// Whether sockets passed by systemd were already adopted
+#[cfg(unix)]
+static SYSTEMD_ADOPTED: AtomicBool = AtomicBool::new(false);
+
+/// Listener accepting connections (TCP or Unix domain socket)
+#[derive(Debug)]
+pub enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    /// Bind TCP listener to address
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+        Ok(Self::Tcp(TcpListener::bind(addr)?))
+    }
+
+    #[cfg(unix)]
+    /// Bind Unix domain socket listener to path<br>
+    /// A stale socket file (no process listening) is removed, mode sets the file permissions (e.g. 0o660)
+    pub fn bind_unix(path: impl AsRef<Path>, mode: Option<u32>) -> Result<Self> {
+        let path = path.as_ref();
+
+        // remove stale socket
+        if let Ok(metadata) = symlink_metadata(path) {
+            if !metadata.file_type().is_socket() {
+                return Fail::from("Unix socket path exists and is not a socket");
+            }
+            match UnixStream::connect(path) {
+                Ok(_) => return Fail::from("Unix socket is already in use"),
+                Err(err) if err.kind() == ErrorKind::ConnectionRefused => remove_file(path)?,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        // bind and set permissions
+        let listener = UnixListener::bind(path)?;
+        if let Some(mode) = mode {
+            set_permissions(path, Permissions::from_mode(mode))?;
+        }
+        Ok(Self::Unix(listener, path.to_path_buf()))
+    }
+
+    #[cfg(unix)]
+    /// Adopt listening sockets passed by systemd socket activation (LISTEN_FDS/LISTEN_PID)<br>
+    /// Returns no listeners if none were passed to this process or they were already adopted
+    pub fn systemd() -> Result<Vec<Self>> {
+        // check if sockets are meant for this process
+        match var("LISTEN_PID").map(|pid| pid.parse::<u32>()) {
+            Ok(Ok(pid)) if pid == process::id() => {}
+            _ => return Ok(Vec::new()),
+        }
+        let fds: RawFd = var("LISTEN_FDS")?.parse()?;
+        let end = match SD_LISTEN_FDS_START.checked_add(fds) {
+            Some(end) if fds >= 0 => end,
+            _ => return Fail::from("Invalid number of systemd sockets (LISTEN_FDS)"),
+        };
+
+        // adopt only once, file descriptors must not be owned twice
+        if SYSTEMD_ADOPTED.swap(true, Ordering::SeqCst) {
+            return Ok(Vec::new());
+        }
+
+        // own all file descriptors first, so they are closed if one is rejected
+        let fds: Vec<OwnedFd> = (SD_LISTEN_FDS_START..end)
+            // SAFETY: systemd passes listening sockets starting at SD_LISTEN_FDS_START,
+            // which are owned by nobody else as they are only adopted once
+            .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) })
+            .collect();
+        fds.into_iter()
+            .map(|fd| {
+                prepare_systemd_fd(fd.as_raw_fd())?;
+                let listener = TcpListener::from(fd);
+                if listener.local_addr().is_ok() {
+                    return Ok(Self::Tcp(listener));
+                }
+
+                // not an IP socket, so it must be a Unix domain socket
+                let listener = UnixListener::from(OwnedFd::from(listener));
+                let path = listener
+                    .local_addr()?
+                    .as_pathname()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_default();
+                Ok(Self::Unix(listener, path))
+            })
+            .collect()
+    }
+
+    /// Get local socket address of TCP listener
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Self::Tcp(listener) => listener.local_addr().ok(),
+            #[cfg(unix)]
+            Self::Unix(_, _) => None,
+        }
+    }
+
+    /// Accept new connection
+    pub fn accept(&self) -> IoResult<(Connection, PeerAddr)> {
+        match self {
+            Self::Tcp(listener) => listener
+                .accept()
+                .map(|(stream, address)| (Connection::Tcp(stream), PeerAddr::Tcp(address))),
+            #[cfg(unix)]
+            Self::Unix(listener, path) => listener
+                .accept()
+                .map(|(stream, _)| (Connection::Unix(stream), PeerAddr::Unix(path.clone()))),
+        }
+    }
+}
+
+#[cfg(unix)]
+/// Set FD_CLOEXEC on a socket passed by systemd and check that it is a stream socket
+fn prepare_systemd_fd(fd: RawFd) -> Result<()> {
+    // don't leak into child processes
+    // SAFETY: fcntl only reads and sets the flags of the file descriptor
+    let flags = unsafe { libc::fcntl(fd, F_GETFD) };
+    if flags < 0 || unsafe { libc::fcntl(fd, F_SETFD, flags | FD_CLOEXEC) } < 0 {
+        return Err(IoError::last_os_error().into());
+    }
+
+    // only stream sockets can be listeners
+    let mut socket_type: c_int = 0;
+    let mut length = size_of::<c_int>() as socklen_t;
+    // SAFETY: socket_type and length are valid for writes of the given length
+    let result = unsafe {
+        libc::getsockopt(
+            fd,
+            SOL_SOCKET,
+            SO_TYPE,
+            (&mut socket_type as *mut c_int).cast(),
+            &mut length,
+        )
+    };
+    if result < 0 {
+        return Err(IoError::last_os_error().into());
+    } else if socket_type != SOCK_STREAM {
+        return Fail::from("Socket passed by systemd is not a stream socket");
+    }
+    Ok(())
+}
+
+/// Listener of an HttpServer with its TLS configuration
+#[derive(Debug)]
+pub struct ServerListener {
+    listener: Listener,
+    #[cfg(feature = "tls")]
+    tls_config: Option<TlsConfigProvider>,
+    #[cfg(feature = "tls")]
+    alpn_cache: Mutex<Option<(TlsConfig, TlsConfig)>>,
+}
+
+impl ServerListener {
+    /// Create new ServerListener without TLS
+    pub fn new(listener: Listener) -> Self {
+        Self {
+            listener,
+            #[cfg(feature = "tls")]
+            tls_config: None,
+            #[cfg(feature = "tls")]
+            alpn_cache: Mutex::default(),
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    /// Set TLS configuration (TLS enabled when Some)
+    pub fn tls(mut self, tls_config: Option<TlsConfigProvider>) -> Self {
+        self.tls_config = tls_config;
+        self
+    }
+
+    /// Get Listener
+    pub fn listener(&self) -> &Listener {
+        &self.listener
+    }
+
+    #[cfg(feature = "tls")]
+    /// Get the TLS configuration provider (e.g. to reload certificates)
+    pub fn tls_provider(&self) -> Option<&TlsConfigProvider> {
+        self.tls_config.as_ref()
+    }
+
+    #[cfg(feature = "tls")]
+    /// Get the current TLS configuration with ALPN protocols (h2, http/1.1) if not set
+    pub(crate) fn alpn_tls_config(&self, http2: bool) -> Option<TlsConfig> {
+        let tls_config = self.tls_config.as_ref()?.config();
+        if !http2 || !tls_config.alpn_protocols.is_empty() {
+            return Some(tls_config);
+        }
+
+        // reuse if provided configuration didn't change
+        let mut alpn_cache = self.alpn_cache.lock().unwrap();
+        if let Some((original, alpn)) = alpn_cache.as_ref()
+            && Arc::ptr_eq(original, &tls_config)
+        {
+            return Some(alpn.clone());
+        }
+
+        // clone configuration and set ALPN protocols
+        let mut alpn = (*tls_config).clone();
+        alpn.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        let alpn = Arc::new(alpn);
+        *alpn_cache = Some((tls_config, alpn.clone()));
+        Some(alpn)
+    }
+}
+
+impl From<Listener> for ServerListener {
+    fn from(listener: Listener) -> Self {
+        Self::new(listener)
+    }
+}
+
+/// Accepted connection
+#[derive(Debug)]
+pub enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Connection {
+    /// Set read timeout
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        match self {
+            Self::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+
+    /// Set write timeout
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> IoResult<()> {
+        match self {
+            Self::Tcp(stream) => stream.set_write_timeout(timeout),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.set_write_timeout(timeout),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        match self {
+            Self::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        match self {
+            Self::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        match self {
+            Self::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Self::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Address of the connected peer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PeerAddr {
+    /// TCP peer with socket address
+    Tcp(SocketAddr),
+
+    #[cfg(unix)]
+    /// Unix domain socket peer with the path of the listening socket
+    Unix(PathBuf),
+}
+
+impl PeerAddr {
+    /// Check whether peer is on the local machine (loopback or Unix domain socket)
+    pub fn is_local(&self) -> bool {
+        match self {
+            Self::Tcp(address) => address.ip().is_loopback(),
+            #[cfg(unix)]
+            Self::Unix(_) => true,
+        }
+    }
+
+    /// Get socket address of TCP peer
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Self::Tcp(address) => Some(*address),
+            #[cfg(unix)]
+            Self::Unix(_) => None,
+        }
+    }
+}
+
+impl From<SocketAddr> for PeerAddr {
+    fn from(address: SocketAddr) -> Self {
+        Self::Tcp(address)
+    }
+}
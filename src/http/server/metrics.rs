@@ -0,0 +1,320 @@
+//! Server metrics with Prometheus text exposition
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use super::HttpMethod;
+
+/// Methods in order of counters
+const METHODS: [HttpMethod; 9] = [
+    HttpMethod::Get,
+    HttpMethod::Post,
+    HttpMethod::Put,
+    HttpMethod::Patch,
+    HttpMethod::Delete,
+    HttpMethod::Head,
+    HttpMethod::Connect,
+    HttpMethod::Options,
+    HttpMethod::Trace,
+];
+
+/// Status classes in order of counters
+const STATUS_CLASSES: [&str; 6] = ["1xx", "2xx", "3xx", "4xx", "5xx", "other"];
+
+/// Default latency buckets in seconds
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Server metrics (counters and latency histogram)
+#[derive(Debug)]
+pub struct Metrics {
+    requests: [[AtomicU64; STATUS_CLASSES.len()]; METHODS.len()],
+    latency: Histogram,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    active_connections: AtomicU64,
+    tls_handshake_failures: AtomicU64,
+    handler_errors: AtomicU64,
+    protocol_errors: AtomicU64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    /// Create new Metrics with all counters zero
+    pub fn new() -> Self {
+        Self {
+            requests: Default::default(),
+            latency: Histogram::new(&LATENCY_BUCKETS),
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            active_connections: AtomicU64::new(0),
+            tls_handshake_failures: AtomicU64::new(0),
+            handler_errors: AtomicU64::new(0),
+            protocol_errors: AtomicU64::new(0),
+        }
+    }
+
+    /// Get number of requests by method and status class (e.g. 2 for 2xx, 0 for other)
+    pub fn requests(&self, method: &HttpMethod, status_class: u16) -> u64 {
+        let class = match status_class {
+            1..=5 => status_class as usize - 1,
+            _ => STATUS_CLASSES.len() - 1,
+        };
+        self.requests[method_index(method)][class].load(Ordering::Relaxed)
+    }
+
+    /// Get number of all requests
+    pub fn requests_total(&self) -> u64 {
+        self.requests
+            .iter()
+            .flatten()
+            .map(|count| count.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Get request latency histogram
+    pub fn latency(&self) -> &Histogram {
+        &self.latency
+    }
+
+    /// Get number of received request bytes (header and body)
+    pub fn bytes_in(&self) -> u64 {
+        self.bytes_in.load(Ordering::Relaxed)
+    }
+
+    /// Get number of sent response bytes
+    pub fn bytes_out(&self) -> u64 {
+        self.bytes_out.load(Ordering::Relaxed)
+    }
+
+    /// Get number of open connections
+    pub fn active_connections(&self) -> u64 {
+        self.active_connections.load(Ordering::Relaxed)
+    }
+
+    /// Get number of failed TLS handshakes
+    pub fn tls_handshake_failures(&self) -> u64 {
+        self.tls_handshake_failures.load(Ordering::Relaxed)
+    }
+
+    /// Get number of errors returned by the handler
+    pub fn handler_errors(&self) -> u64 {
+        self.handler_errors.load(Ordering::Relaxed)
+    }
+
+    /// Get number of requests rejected before reaching the handler (e.g. malformed headers)
+    pub fn protocol_errors(&self) -> u64 {
+        self.protocol_errors.load(Ordering::Relaxed)
+    }
+
+    /// Record handled request
+    pub(crate) fn record_request(&self, method: &HttpMethod, status: u16, latency: Duration) {
+        self.requests[method_index(method)][status_class_index(status)]
+            .fetch_add(1, Ordering::Relaxed);
+        self.latency.observe(latency);
+    }
+
+    /// Record received bytes
+    pub(crate) fn record_bytes_in(&self, bytes: usize) {
+        self.bytes_in.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Record sent bytes
+    pub(crate) fn record_bytes_out(&self, bytes: usize) {
+        self.bytes_out.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "tls")]
+    /// Record failed TLS handshake
+    pub(crate) fn record_tls_handshake_failure(&self) {
+        self.tls_handshake_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record error returned by the handler
+    pub(crate) fn record_handler_error(&self) {
+        self.handler_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record request rejected before reaching the handler
+    pub(crate) fn record_protocol_error(&self) {
+        self.protocol_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count connection as active until guard is dropped
+    pub(crate) fn connection(&self) -> ConnectionGuard<'_> {
+        self.active_connections.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard(self)
+    }
+
+    /// Render in Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+
+        // requests by method and status class
+        text.push_str("# HELP kern_http_requests_total Handled HTTP requests\n");
+        text.push_str("# TYPE kern_http_requests_total counter\n");
+        for (method, counts) in METHODS.iter().zip(&self.requests) {
+            for (class, count) in STATUS_CLASSES.iter().zip(counts) {
+                let count = count.load(Ordering::Relaxed);
+                if count > 0 {
+                    writeln!(
+                        text,
+                        "kern_http_requests_total{{method=\"{}\",status=\"{class}\"}} {count}",
+                        method.as_str()
+                    )
+                    .ok();
+                }
+            }
+        }
+
+        // latency histogram
+        self.latency.render(
+            &mut text,
+            "kern_http_request_duration_seconds",
+            "HTTP request latency",
+        );
+
+        // counters and gauges
+        for (name, kind, help, value) in [
+            (
+                "kern_http_request_bytes_total",
+                "counter",
+                "Received request bytes",
+                self.bytes_in(),
+            ),
+            (
+                "kern_http_response_bytes_total",
+                "counter",
+                "Sent response bytes",
+                self.bytes_out(),
+            ),
+            (
+                "kern_http_active_connections",
+                "gauge",
+                "Open connections",
+                self.active_connections(),
+            ),
+            (
+                "kern_http_tls_handshake_failures_total",
+                "counter",
+                "Failed TLS handshakes",
+                self.tls_handshake_failures(),
+            ),
+            (
+                "kern_http_handler_errors_total",
+                "counter",
+                "Errors returned by the handler",
+                self.handler_errors(),
+            ),
+            (
+                "kern_http_protocol_errors_total",
+                "counter",
+                "Requests rejected before reaching the handler",
+                self.protocol_errors(),
+            ),
+        ] {
+            writeln!(
+                text,
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}"
+            )
+            .ok();
+        }
+        text
+    }
+}
+
+/// Decrements active connections when dropped
+pub(crate) struct ConnectionGuard<'a>(&'a Metrics);
+
+impl Drop for ConnectionGuard<'_> {
+    fn drop(&mut self) {
+        self.0.active_connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Histogram with cumulative buckets (upper bounds in seconds)
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: Vec<f64>,
+    buckets: Vec<AtomicU64>,
+    sum_micros: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    /// Create new Histogram with bucket upper bounds in seconds
+    pub fn new(bounds: &[f64]) -> Self {
+        Self {
+            bounds: bounds.to_vec(),
+            buckets: bounds.iter().map(|_| AtomicU64::new(0)).collect(),
+            sum_micros: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    /// Record observed duration
+    pub fn observe(&self, duration: Duration) {
+        let secs = duration.as_secs_f64();
+        if let Some(bucket) = self.bounds.iter().position(|&bound| secs <= bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Get number of observations
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Get sum of observations
+    pub fn sum(&self) -> Duration {
+        Duration::from_micros(self.sum_micros.load(Ordering::Relaxed))
+    }
+
+    /// Get cumulative counts as (upper bound in seconds, count)
+    pub fn buckets(&self) -> Vec<(f64, u64)> {
+        let mut cumulative = 0;
+        self.bounds
+            .iter()
+            .zip(&self.buckets)
+            .map(|(&bound, count)| {
+                cumulative += count.load(Ordering::Relaxed);
+                (bound, cumulative)
+            })
+            .collect()
+    }
+
+    /// Render in Prometheus text exposition format
+    fn render(&self, text: &mut String, name: &str, help: &str) {
+        writeln!(text, "# HELP {name} {help}\n# TYPE {name} histogram").ok();
+        for (bound, count) in self.buckets() {
+            writeln!(text, "{name}_bucket{{le=\"{bound}\"}} {count}").ok();
+        }
+        let count = self.count();
+        writeln!(text, "{name}_bucket{{le=\"+Inf\"}} {count}").ok();
+        writeln!(text, "{name}_sum {}", self.sum().as_secs_f64()).ok();
+        writeln!(text, "{name}_count {count}").ok();
+    }
+}
+
+/// Index of method in counters
+fn method_index(method: &HttpMethod) -> usize {
+    METHODS.iter().position(|m| m == method).unwrap_or(0)
+}
+
+/// Index of status class in counters
+fn status_class_index(status: u16) -> usize {
+    match status {
+        100..=599 => (status / 100 - 1) as usize,
+        _ => STATUS_CLASSES.len() - 1,
+    }
+}
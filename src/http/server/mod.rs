@@ -1,26 +1,59 @@
 //! HTTP server
 
+mod auth;
 mod builder;
+#[cfg(feature = "tls")]
+mod certificate;
+mod conditional;
+mod cors;
+mod error;
+mod hpack;
+mod http2;
+mod listener;
+mod metrics;
+mod negotiation;
 mod request;
 mod response;
 #[allow(clippy::module_inception)]
 mod server;
 mod settings;
+mod testing;
 #[cfg(feature = "tls")]
 mod tls;
 
+pub use auth::*;
 pub use builder::*;
+#[cfg(feature = "tls")]
+pub use certificate::*;
+pub use conditional::*;
+pub use cors::*;
+pub use error::*;
+pub use listener::*;
+pub use metrics::*;
+pub use negotiation::*;
 pub use request::*;
 pub use response::*;
 pub use server::*;
 pub use settings::*;
+pub use testing::*;
 #[cfg(feature = "tls")]
 pub use tls::*;
 
-use crate::{Error, Result};
+use crate::Result;
+
+use std::collections::HashMap;
 
 /// Handler function
 pub type Handler = fn(HttpRequest) -> Result<Vec<u8>>;
 
-/// ErrorHandler function
-pub type ErrorHandler = fn(Error) -> Vec<u8>;
+/// ErrorHandler function, receives error with status and request context
+pub type ErrorHandler = fn(ErrorContext) -> Vec<u8>;
+
+/// Called with parsed request before it is handled
+pub type StartHook = fn(&HttpRequest);
+
+/// Called with summary after request was handled
+pub type EndHook = fn(&RequestSummary);
+
+/// Decides on Expect: 100-continue based on method, URL and headers (Some rejects with response)
+pub type ContinueHandler = fn(&HttpMethod, &str, &HashMap<String, &str>) -> Option<Vec<u8>>;
@@ -0,0 +1,129 @@
+//! Content negotiation (Accept, Accept-Language, Accept-Encoding and Accept-Charset)
+
+use super::{ResponseData, respond};
+
+/// Value of an Accept* header with quality (q-value)
+#[derive(Clone, Debug, PartialEq)]
+pub struct QualityItem<'a> {
+    pub value: &'a str,
+    pub quality: f32,
+}
+
+/// Kind of content negotiation (header and matching rules)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Negotiation {
+    /// Media type using Accept (e.g. "text/html", "text/*", "*/*")
+    MediaType,
+
+    /// Language tag using Accept-Language (e.g. "de-DE" matched by "de")
+    Language,
+
+    /// Content coding using Accept-Encoding (identity acceptable unless excluded)
+    Encoding,
+
+    /// Charset using Accept-Charset
+    Charset,
+}
+
+impl Negotiation {
+    /// Get lowercase header name
+    pub fn header(&self) -> &'static str {
+        match self {
+            Self::MediaType => "accept",
+            Self::Language => "accept-language",
+            Self::Encoding => "accept-encoding",
+            Self::Charset => "accept-charset",
+        }
+    }
+
+    /// Specificity of range matching offered value, None if not matching
+    fn specificity(&self, range: &str, offered: &str) -> Option<usize> {
+        if range == "*" || range == "*/*" {
+            return Some(0);
+        }
+        match self {
+            Self::MediaType => match range.strip_suffix("/*") {
+                Some(range_type) => offered
+                    .split_once('/')
+                    .filter(|(offered_type, _)| offered_type.eq_ignore_ascii_case(range_type))
+                    .map(|_| 1),
+                None => range.eq_ignore_ascii_case(offered).then_some(2),
+            },
+            Self::Language => {
+                let prefix = offered.get(..range.len())?;
+                let rest = &offered[range.len()..];
+                (prefix.eq_ignore_ascii_case(range) && (rest.is_empty() || rest.starts_with('-')))
+                    .then_some(range.len())
+            }
+            Self::Encoding | Self::Charset => range.eq_ignore_ascii_case(offered).then_some(1),
+        }
+    }
+
+    /// Quality of offered value for parsed preferences
+    fn quality(&self, preferences: &[QualityItem], offered: &str) -> f32 {
+        let quality = preferences
+            .iter()
+            .filter_map(|item| Some((self.specificity(item.value, offered)?, item.quality)))
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, quality)| quality);
+
+        // identity is acceptable if not excluded
+        match quality {
+            None if *self == Self::Encoding && offered.eq_ignore_ascii_case("identity") => 0.001,
+            quality => quality.unwrap_or_default(),
+        }
+    }
+
+    /// Pick best offered value (first offered wins ties), None if nothing is acceptable<br>
+    /// Every offered value is acceptable if header is None
+    pub fn best<'o>(&self, header: Option<&str>, offered: &[&'o str]) -> Option<&'o str> {
+        let Some(header) = header else {
+            return offered.first().copied();
+        };
+        let preferences = parse_quality_list(header);
+        offered
+            .iter()
+            .map(|offered| (*offered, self.quality(&preferences, offered)))
+            .filter(|(_, quality)| *quality > 0.0)
+            .fold(
+                None,
+                |best: Option<(&str, f32)>, (offered, quality)| match best {
+                    Some((_, best_quality)) if best_quality >= quality => best,
+                    _ => Some((offered, quality)),
+                },
+            )
+            .map(|(offered, _)| offered)
+    }
+}
+
+/// Parse Accept* header to values sorted by quality (highest first)
+pub fn parse_quality_list(header: &str) -> Vec<QualityItem<'_>> {
+    let mut items: Vec<QualityItem> = header
+        .split(',')
+        .filter_map(|item| {
+            // split value and parameters
+            let mut parts = item.split(';').map(str::trim);
+            let value = parts.next().filter(|value| !value.is_empty())?;
+            let quality = match parts.find_map(|param| {
+                param
+                    .strip_prefix("q=")
+                    .or_else(|| param.strip_prefix("Q="))
+            }) {
+                Some(quality) => quality.parse::<f32>().ok()?.clamp(0.0, 1.0),
+                None => 1.0,
+            };
+            Some(QualityItem { value, quality })
+        })
+        .collect();
+    items.sort_by(|a, b| b.quality.total_cmp(&a.quality));
+    items
+}
+
+/// Create 406 Not Acceptable response
+pub fn not_acceptable() -> Vec<u8> {
+    respond(
+        "Not Acceptable",
+        "text/plain",
+        ResponseData::not_acceptable().build(),
+    )
+}
@@ -1,11 +1,21 @@
 //! HTTP request parsing
 
-use crate::byte::{split, splitn};
-use crate::http::common::ReadWrite;
-use crate::http::server::HttpSettings;
+use crate::byte::{base64_decode, split, splitn};
+use crate::http::common::{ReadWrite, generate_id};
+use crate::http::server::{
+    HttpError, HttpSettings, Negotiation, PeerAddr, QualityItem, ResponseData, not_acceptable,
+    parse_quality_list, respond,
+};
+use crate::http::url::Url;
 use crate::{Fail, Result};
 
-use std::{collections::HashMap, net::SocketAddr};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::time::Duration;
+
+#[cfg(feature = "tls")]
+use crate::http::server::ClientCertificate;
 
 pub use crate::http::common::HttpMethod;
 
@@ -14,11 +24,17 @@ pub use crate::http::common::HttpMethod;
 pub struct HttpRequest<'a> {
     method: HttpMethod,
     url: &'a str,
+    full_url: Url,
     headers: HashMap<String, &'a str>,
     get: HashMap<String, &'a str>,
     post: HashMap<String, Vec<u8>>,
     ip: String,
+    peer_addr: PeerAddr,
+    head: bool,
+    id: String,
     body: Vec<u8>,
+    #[cfg(feature = "tls")]
+    client_certificate: Option<ClientCertificate>,
 }
 
 impl<'a> HttpRequest<'a> {
@@ -28,12 +44,31 @@ impl<'a> HttpRequest<'a> {
         &self.method
     }
 
+    /// Check whether request was received as HEAD and is handled as GET
+    pub fn head(&self) -> bool {
+        // return whether HEAD request
+        self.head
+    }
+
+    /// Handle HEAD request as GET
+    pub(crate) fn head_as_get(&mut self) {
+        if self.method == HttpMethod::Head {
+            self.method = HttpMethod::Get;
+            self.head = true;
+        }
+    }
+
     /// Get URL
     pub fn url(&self) -> &str {
         // return URL
         self.url
     }
 
+    /// Get full URL of connection scheme, Host header (localhost if empty) and target (or absolute-form target)
+    pub fn full_url(&self) -> &Url {
+        &self.full_url
+    }
+
     /// Get headers map
     pub fn headers(&self) -> &HashMap<String, &str> {
         // return headers map
@@ -71,47 +106,140 @@ impl<'a> HttpRequest<'a> {
         &self.body
     }
 
-    /// Get IP address
+    /// Get Basic authentication credentials (user, password)
+    pub fn basic_auth(&self) -> Option<(String, String)> {
+        // decode credentials and split at first colon
+        let credentials = base64_decode(self.authorization("basic")?).ok()?;
+        let credentials = String::from_utf8(credentials).ok()?;
+        let (user, password) = credentials.split_once(':')?;
+        Some((user.to_string(), password.to_string()))
+    }
+
+    /// Get Bearer token
+    pub fn bearer_token(&self) -> Option<&str> {
+        // return token
+        self.authorization("bearer")
+    }
+
+    /// Get credentials of authorization header with scheme (lowercase)
+    fn authorization(&self, scheme: &str) -> Option<&str> {
+        let (auth_scheme, credentials) = self.headers.get("authorization")?.split_once(' ')?;
+        let credentials = credentials.trim();
+        (auth_scheme.eq_ignore_ascii_case(scheme) && !credentials.is_empty()).then_some(credentials)
+    }
+
+    /// Get Accept media ranges sorted by quality
+    pub fn accept(&self) -> Vec<QualityItem<'a>> {
+        self.quality_list(Negotiation::MediaType)
+    }
+
+    /// Get Accept-Language ranges sorted by quality
+    pub fn accept_language(&self) -> Vec<QualityItem<'a>> {
+        self.quality_list(Negotiation::Language)
+    }
+
+    /// Get Accept-Encoding codings sorted by quality
+    pub fn accept_encoding(&self) -> Vec<QualityItem<'a>> {
+        self.quality_list(Negotiation::Encoding)
+    }
+
+    /// Get Accept-Charset charsets sorted by quality
+    pub fn accept_charset(&self) -> Vec<QualityItem<'a>> {
+        self.quality_list(Negotiation::Charset)
+    }
+
+    /// Parse Accept* header of negotiation kind
+    fn quality_list(&self, kind: Negotiation) -> Vec<QualityItem<'a>> {
+        self.headers
+            .get(kind.header())
+            .map(|header| parse_quality_list(header))
+            .unwrap_or_default()
+    }
+
+    /// Pick best offered media type (e.g. ["text/html", "application/json"]) or 406 response
+    pub fn negotiate<'o>(&self, offered: &[&'o str]) -> std::result::Result<&'o str, Vec<u8>> {
+        self.negotiate_with(Negotiation::MediaType, offered)
+    }
+
+    /// Pick best offered value for negotiation kind or 406 response
+    pub fn negotiate_with<'o>(
+        &self,
+        kind: Negotiation,
+        offered: &[&'o str],
+    ) -> std::result::Result<&'o str, Vec<u8>> {
+        let header = self.headers.get(kind.header()).copied();
+        kind.best(header, offered).ok_or_else(not_acceptable)
+    }
+
+    /// Get request ID (from X-Request-Id or traceparent header, otherwise generated)
+    pub fn id(&self) -> &str {
+        // return request id
+        &self.id
+    }
+
+    /// Get IP address ("unix" for Unix domain socket peers without x-real-ip)
     pub fn ip(&self) -> &str {
         // return IP address string
         &self.ip
     }
 
-    /// Parse HTTP request
+    /// Get address of the connected peer
+    pub fn peer_addr(&self) -> &PeerAddr {
+        // return peer address
+        &self.peer_addr
+    }
+
+    #[cfg(feature = "tls")]
+    /// Get verified client certificate (mutual TLS)
+    pub fn client_certificate(&self) -> Option<&ClientCertificate> {
+        // return client certificate
+        self.client_certificate.as_ref()
+    }
+
+    /// Use https as scheme of full URL if received over TLS
+    pub(crate) fn set_secure(&mut self, secure: bool) {
+        if secure && self.full_url.scheme() == "http" {
+            self.full_url.set_scheme("https").ok();
+        }
+    }
+
+    #[cfg(feature = "tls")]
+    /// Set verified client certificate
+    pub(crate) fn set_client_certificate(&mut self, client_certificate: Option<ClientCertificate>) {
+        self.client_certificate = client_certificate;
+    }
+
+    /// Parse HTTP request, fails with 400 for an invalid Host header (RFC 9112 3.2)
     pub fn from(
         raw_header: &'a str,
         mut partial_body: Vec<u8>,
         stream: &mut impl ReadWrite,
-        address: SocketAddr,
+        address: impl Into<PeerAddr>,
         settings: &HttpSettings,
     ) -> Result<Self> {
         // split header
         let mut header = raw_header.lines();
         let mut reqln = header
             .next()
-            .ok_or_else(|| Fail::new("Empty header"))?
+            .ok_or_else(|| HttpError::bad_request("Empty header"))?
             .split(' ');
 
         // parse method
         let method: HttpMethod = reqln
             .next()
-            .ok_or_else(|| Fail::new("No method in header"))?
-            .try_into()?;
-
-        // parse url and split raw get parameters
-        let mut get_raw = "";
-        let url = if let Some(full_url) = reqln.next() {
-            let mut split_url = full_url.splitn(2, '?');
-            let url = split_url
-                .next()
-                .ok_or_else(|| Fail::new("No URL in header"))?;
-            if let Some(params) = split_url.next() {
-                get_raw = params;
-            }
-            url
-        } else {
-            "/"
+            .ok_or_else(|| HttpError::bad_request("No method in header"))?
+            .try_into()
+            .map_err(HttpError::method_not_allowed)?;
+
+        // parse url and split raw get parameters, path of absolute-form (e.g. from proxies)
+        let target = reqln.next().unwrap_or("/");
+        let absolute = !target.starts_with('/') && target.contains("://");
+        let origin_form = match target.split_once("://") {
+            Some((_, rest)) if absolute => &rest[rest.find('/').unwrap_or(rest.len())..],
+            _ => target,
         };
+        let (url, get_raw) = origin_form.split_once('?').unwrap_or((origin_form, ""));
+        let url = if url.is_empty() { "/" } else { url };
 
         // parse headers
         let mut headers = HashMap::new();
@@ -122,6 +250,24 @@ impl<'a> HttpRequest<'a> {
             }
         });
 
+        // full url of absolute-form or Host header
+        let full_url = match absolute {
+            true => Url::parse(target),
+            false => {
+                let host = headers.get("host").copied().filter(|host| !host.is_empty());
+                let host = host.unwrap_or("localhost");
+                // userinfo, path, query or fragment must not be smuggled into the URL
+                if host.contains(['@', '/', '\\', '?', '#'])
+                    || host.chars().any(|c| c.is_whitespace() || c.is_control())
+                {
+                    return Err(HttpError::bad_request("Invalid Host header"));
+                }
+                let path = if target.starts_with('/') { target } else { "/" };
+                Url::parse(&format!("http://{host}{path}"))
+            }
+        }
+        .map_err(HttpError::bad_request)?;
+
         // get content length
         let buf_len = if let Some(buf_len) = headers.get("Content-Length") {
             Some(buf_len)
@@ -135,11 +281,18 @@ impl<'a> HttpRequest<'a> {
             let con_len = buf_len
                 .parse::<usize>()
                 .ok()
-                .ok_or_else(|| Fail::new("Content-Length is not of type usize"))?;
+                .ok_or_else(|| HttpError::bad_request("Content-Length is not of type usize"))?;
+
+            // answer expectation before reading body
+            if let Some(expect) = headers.get("expect")
+                && partial_body.len() < con_len
+            {
+                expect_continue(&method, url, &headers, expect, con_len, stream, settings)?;
+            }
 
             // check if body size is ok.
             if con_len > settings.max_body_size {
-                return Fail::from("Max body size exceeded");
+                return Err(HttpError::content_too_large("Max body size exceeded"));
             }
 
             // read body
@@ -150,7 +303,7 @@ impl<'a> HttpRequest<'a> {
                 let length = stream
                     .read(&mut rest_body)
                     .ok()
-                    .ok_or_else(|| Fail::new("Stream broken"))?;
+                    .ok_or_else(|| HttpError::bad_request("Stream broken"))?;
                 rest_body.truncate(length);
                 partial_body.append(&mut rest_body);
 
@@ -160,34 +313,125 @@ impl<'a> HttpRequest<'a> {
 
                     // failed too often
                     if read_fails > settings.body_read_attempts {
-                        return Fail::from("Read body failed too often");
+                        return Err(HttpError::bad_request("Read body failed too often"));
                     }
                 }
             }
         }
 
         // parse GET and POST parameters
-        let get = parse_parameters(get_raw, |v| v)?;
+        let get = parse_parameters(get_raw, |v| v).map_err(HttpError::bad_request)?;
         let post = parse_post(&headers, &partial_body).unwrap_or_default();
 
-        // ip: x-real-ip if socket ip is loopback else socket ip
-        let ip = match headers.get("x-real-ip") {
-            Some(x_real_ip) if address.ip().is_loopback() => x_real_ip.to_string(),
-            _ => address.ip().to_string(),
+        // ip: x-real-ip if peer is local (loopback or unix socket) else socket ip
+        let peer_addr = address.into();
+        let ip = match (headers.get("x-real-ip"), peer_addr.socket_addr()) {
+            (Some(x_real_ip), _) if peer_addr.is_local() => x_real_ip.to_string(),
+            (_, Some(address)) => address.ip().to_string(),
+            (_, None) => "unix".to_string(),
         };
 
+        // request id: valid x-request-id, trace id of traceparent or generated
+        let id = headers
+            .get("x-request-id")
+            .filter(|id| (1..=200).contains(&id.len()) && id.bytes().all(|b| b.is_ascii_graphic()))
+            .map(|id| id.to_string())
+            .or_else(|| trace_id(headers.get("traceparent")?))
+            .unwrap_or_else(generate_id);
+
         Ok(Self {
             method,
             url,
+            full_url,
             headers,
             get,
             post,
             ip,
+            peer_addr,
+            head: false,
+            id,
             body: partial_body,
+            #[cfg(feature = "tls")]
+            client_certificate: None,
         })
     }
 }
 
+/// Summary of handled request
+#[derive(Clone, Debug)]
+pub struct RequestSummary {
+    pub id: String,
+    pub method: HttpMethod,
+    pub url: String,
+    pub ip: String,
+    pub status: u16,
+    pub duration: Duration,
+}
+
+/// Get trace id of W3C traceparent header (version-traceid-parentid-flags)
+fn trace_id(traceparent: &str) -> Option<String> {
+    let parts: Vec<&str> = traceparent.trim().split('-').collect();
+    let hex = |part: &str, len| part.len() == len && part.bytes().all(|b| b.is_ascii_hexdigit());
+    match parts[..] {
+        [version, trace, parent, flags, ..]
+            if hex(version, 2)
+                && version != "ff"
+                && hex(trace, 32)
+                && hex(parent, 16)
+                && hex(flags, 2)
+                && trace.bytes().any(|b| b != b'0') =>
+        {
+            Some(trace.to_lowercase())
+        }
+        _ => None,
+    }
+}
+
+/// Error carrying a response to send instead of handling the request (e.g. rejected expectation)
+#[derive(Debug)]
+pub struct EarlyResponse(pub Vec<u8>);
+
+impl Display for EarlyResponse {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "request answered early")
+    }
+}
+
+impl Error for EarlyResponse {}
+
+/// Send 100 Continue or reject with 417/413 or response of ContinueHandler
+fn expect_continue(
+    method: &HttpMethod,
+    url: &str,
+    headers: &HashMap<String, &str>,
+    expect: &str,
+    content_length: usize,
+    stream: &mut impl ReadWrite,
+    settings: &HttpSettings,
+) -> Result<()> {
+    // check expectation and body size
+    if !expect.eq_ignore_ascii_case("100-continue") {
+        let data = ResponseData::expectation_failed().build();
+        return Err(EarlyResponse(respond("Expectation Failed", "text/plain", data)).into());
+    } else if content_length > settings.max_body_size {
+        let data = ResponseData::content_too_large().build();
+        return Err(EarlyResponse(respond("Content Too Large", "text/plain", data)).into());
+    }
+
+    // let handler decide based on headers
+    if let Some(response) = settings
+        .continue_handler
+        .and_then(|continue_handler| continue_handler(method, url, headers))
+    {
+        return Err(EarlyResponse(response).into());
+    }
+
+    // tell client to send body
+    stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+    stream.flush()?;
+    Ok(())
+}
+
 /// Parse POST parameters to map
 fn parse_post(headers: &HashMap<String, &str>, body: &[u8]) -> Result<HashMap<String, Vec<u8>>> {
     match headers.get("content-type") {
@@ -2,6 +2,9 @@
 
 use std::collections::HashMap;
 use std::convert::AsRef;
+use std::str;
+
+use crate::byte::scan;
 
 /// Additional response data
 #[derive(Clone, Debug)]
@@ -313,15 +316,24 @@ pub fn respond(
     );
     response.extend_from_slice(header.as_bytes());
 
-    // write content
-    response.append(&mut set_content_length(content.len()));
-    response.extend_from_slice(content);
-    response.extend_from_slice(b"\r\n");
+    // write content (none for 1xx, 204 and 304)
+    if bodyless_status(status) {
+        response.extend_from_slice(b"\r\n\r\n");
+    } else {
+        response.append(&mut set_content_length(content.len()));
+        response.extend_from_slice(content);
+        response.extend_from_slice(b"\r\n");
+    }
 
     // return
     response
 }
 
+/// Check whether status forbids a body (1xx, 204 and 304)
+fn bodyless_status(status: &str) -> bool {
+    status.starts_with('1') || status.starts_with("204") || status.starts_with("304")
+}
+
 /// create content-length header bytes
 fn set_content_length(content_length: usize) -> Vec<u8> {
     let mut header = Vec::new();
@@ -337,12 +349,8 @@ pub fn redirect(url: impl AsRef<str>) -> Vec<u8> {
     // as ref
     let url = url.as_ref();
 
-    // set location
-    let mut headers = HashMap::new();
-    headers.insert("location", url);
-
-    // create response data
-    let data = ResponseData::see_other();
+    // create response data with location
+    let data = ResponseData::see_other().header("location", url);
 
     // create and return response
     respond(
@@ -353,3 +361,105 @@ pub fn redirect(url: impl AsRef<str>) -> Vec<u8> {
         Some(data),
     )
 }
+
+/// Status, headers and body of a raw HTTP response
+pub(crate) type ResponseParts<'a> = (&'a str, Vec<(&'a str, &'a str)>, &'a [u8]);
+
+/// Split raw HTTP response into status (e.g. "200 OK"), headers and body
+pub(crate) fn split_response(response: &[u8]) -> Option<ResponseParts<'_>> {
+    // split header and body
+    let header_end = scan(response, b"\r\n\r\n")?;
+    let header = str::from_utf8(&response[..header_end]).ok()?;
+    let body = &response[header_end + 4..];
+
+    // parse status line and headers
+    let mut lines = header.split("\r\n");
+    let (_, status) = lines.next()?.split_once(' ')?;
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim(), value.trim()))
+        .collect();
+    Some((status, headers, body))
+}
+
+/// Insert headers after the status line of a raw HTTP response, existing headers are kept
+pub(crate) fn insert_headers(response: &mut Vec<u8>, headers: &[(&str, &str)]) {
+    // skip headers already set
+    let existing: Vec<String> = match split_response(response) {
+        Some((_, existing, _)) => existing
+            .iter()
+            .map(|(name, _)| name.to_lowercase())
+            .collect(),
+        None => return,
+    };
+    let mut inserted = Vec::new();
+    headers
+        .iter()
+        .filter(|(name, _)| !existing.contains(&name.to_lowercase()))
+        .for_each(|(name, value)| {
+            inserted.extend_from_slice(b"\r\n");
+            inserted.extend_from_slice(name.as_bytes());
+            inserted.extend_from_slice(b": ");
+            inserted.extend_from_slice(value.as_bytes());
+        });
+
+    // insert at end of status line
+    if let Some(status_end) = scan(response.as_slice(), b"\r\n") {
+        response.splice(status_end..status_end, inserted);
+    }
+}
+
+/// Add value to Vary header of a raw HTTP response, merged into an existing Vary header
+pub(crate) fn add_vary(response: &mut Vec<u8>, value: &str) {
+    let Some((status, headers, body)) = split_response(response) else {
+        return;
+    };
+    let is_vary = |name: &str| name.eq_ignore_ascii_case("vary");
+
+    // already varies on value (or everything)
+    let mut values = headers
+        .iter()
+        .filter(|(name, _)| is_vary(name))
+        .flat_map(|(_, values)| values.split(','))
+        .map(str::trim);
+    if values.any(|vary| vary == "*" || vary.eq_ignore_ascii_case(value)) {
+        return;
+    } else if !headers.iter().any(|(name, _)| is_vary(name)) {
+        return insert_headers(response, &[("vary", value)]);
+    }
+
+    // rebuild header with value appended to first Vary header
+    let mut merged = format!("HTTP/1.1 {status}").into_bytes();
+    let mut appended = false;
+    for (name, existing) in &headers {
+        merged.extend_from_slice(format!("\r\n{name}: {existing}").as_bytes());
+        if !appended && is_vary(name) {
+            merged.extend_from_slice(format!(", {value}").as_bytes());
+            appended = true;
+        }
+    }
+    merged.extend_from_slice(b"\r\n\r\n");
+    merged.extend_from_slice(body);
+    *response = merged;
+}
+
+/// Remove body of raw HTTP response, content-length is kept for HEAD (forbidden for 1xx, 204 and 304)
+pub(crate) fn strip_body(response: &mut Vec<u8>, head: bool) {
+    let Some((status, headers, _)) = split_response(response) else {
+        return;
+    };
+    if !head && !bodyless_status(status) {
+        return;
+    }
+
+    // rebuild header without body
+    let mut stripped = format!("HTTP/1.1 {status}").into_bytes();
+    headers
+        .iter()
+        .filter(|(name, _)| head || !name.eq_ignore_ascii_case("content-length"))
+        .for_each(|(name, value)| {
+            stripped.extend_from_slice(format!("\r\n{name}: {value}").as_bytes());
+        });
+    stripped.extend_from_slice(b"\r\n\r\n");
+    *response = stripped;
+}
@@ -1,31 +1,38 @@
+use std::error::Error;
 #[cfg(not(feature = "tls"))]
 use std::io::prelude::*;
-use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::io::{Error as IoError, ErrorKind};
 use std::panic::catch_unwind;
 use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::thread::{JoinHandle, spawn};
+use std::time::Instant;
 
 #[cfg(feature = "tls")]
 use {
-    super::{TlsConfig, TlsConfigProvider},
+    super::{ClientCertificate, TlsConfig, TlsConfigProvider},
     rustls::{ServerConnection, Stream as RustlsStream},
 };
 
-use crate::http::common::ReadWrite;
+use crate::http::common::{ReadWrite, enter_request};
+use crate::http::date::http_date_now;
 use crate::{Fail, Result};
 
-use super::{ErrorHandler, Handler, HttpRequest, HttpSettings};
+use super::http2::Http2Connection;
+use super::{
+    Connection, EarlyResponse, ErrorContext, ErrorHandler, Handler, HttpError, HttpMethod,
+    HttpRequest, HttpSettings, Listener, Metrics, PeerAddr, RequestContext, RequestSummary,
+    ServerListener, insert_headers, respond, split_response, strip_body,
+};
 
 /// Processes incoming HTTP connections
 #[derive(Debug)]
 pub struct HttpServer {
-    listener: Arc<RwLock<TcpListener>>,
+    listeners: Vec<ServerListener>,
     settings: Arc<HttpSettings>,
     handler: Handler,
-    error_handler: ErrorHandler,
+    pub(crate) error_handler: ErrorHandler,
     threads: RwLock<Vec<JoinHandle<()>>>,
-    #[cfg(feature = "tls")]
-    tls_config: Option<TlsConfigProvider>,
+    metrics: Metrics,
 }
 
 impl HttpServer {
@@ -37,15 +44,47 @@ impl HttpServer {
         error_handler: ErrorHandler,
         #[cfg(feature = "tls")] tls_config: Option<TlsConfigProvider>,
     ) -> Result<Arc<Self>> {
-        let listener = TcpListener::bind(addr)?;
-        let server = Self {
-            listener: Arc::new(RwLock::new(listener)),
+        Self::with_listener(
+            Listener::bind(addr)?,
             settings,
             handler,
             error_handler,
-            threads: RwLock::default(),
             #[cfg(feature = "tls")]
             tls_config,
+        )
+    }
+
+    /// Create new HttpServer using an already bound Listener
+    pub fn with_listener(
+        listener: Listener,
+        settings: Arc<HttpSettings>,
+        handler: Handler,
+        error_handler: ErrorHandler,
+        #[cfg(feature = "tls")] tls_config: Option<TlsConfigProvider>,
+    ) -> Result<Arc<Self>> {
+        let listener = ServerListener::new(listener);
+        #[cfg(feature = "tls")]
+        let listener = listener.tls(tls_config);
+        Self::with_listeners(vec![listener], settings, handler, error_handler)
+    }
+
+    /// Create new HttpServer accepting connections of multiple listeners
+    pub fn with_listeners(
+        listeners: Vec<ServerListener>,
+        settings: Arc<HttpSettings>,
+        handler: Handler,
+        error_handler: ErrorHandler,
+    ) -> Result<Arc<Self>> {
+        if listeners.is_empty() {
+            return Fail::from("no listener for HttpServer");
+        }
+        let server = Self {
+            listeners,
+            settings,
+            handler,
+            error_handler,
+            threads: RwLock::default(),
+            metrics: Metrics::new(),
         };
         let server = Arc::new(server);
 
@@ -55,31 +94,66 @@ impl HttpServer {
             CONSTANT(threads) => (false, threads),
         };
 
-        (0..threads).for_each(|_| {
-            let server_clone = server.clone();
-            server.threads_mut().unwrap().push(spawn(move || {
-                if no_catch {
-                    accept_all(server_clone);
-                } else {
-                    loop {
-                        catch_unwind(|| accept_all(server_clone.clone())).ok();
-                        eprintln!("HTTP thread panicked, restarting...");
+        // spawn threads for each listener
+        for index in 0..server.listeners.len() {
+            (0..threads).for_each(|_| {
+                let server_clone = server.clone();
+                server.threads_mut().unwrap().push(spawn(move || {
+                    if no_catch {
+                        accept_all(server_clone, index);
+                    } else {
+                        loop {
+                            catch_unwind(|| accept_all(server_clone.clone(), index)).ok();
+                            eprintln!("HTTP thread panicked, restarting...");
+                        }
                     }
-                }
-            }));
-        });
+                }));
+            });
+        }
         Ok(server)
     }
 
+    /// Create new HttpServer without listeners (for TestServer)
+    pub(crate) fn detached(
+        settings: Arc<HttpSettings>,
+        handler: Handler,
+        error_handler: ErrorHandler,
+    ) -> Self {
+        Self {
+            listeners: Vec::new(),
+            settings,
+            handler,
+            error_handler,
+            threads: RwLock::default(),
+            metrics: Metrics::new(),
+        }
+    }
+
     /// Get HttpSettings
     pub fn settings(&self) -> &HttpSettings {
         &self.settings
     }
 
+    /// Get Metrics
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Get listeners
+    pub fn listeners(&self) -> &[ServerListener] {
+        &self.listeners
+    }
+
     #[cfg(feature = "tls")]
-    /// Get a new TLS configuration
+    /// Get the current TLS configuration (of the first listener)
     pub fn tls_config(&self) -> Option<TlsConfig> {
-        self.tls_config.map(|f| f())
+        self.tls_provider().map(|provider| provider.config())
+    }
+
+    #[cfg(feature = "tls")]
+    /// Get the TLS configuration provider of the first listener (e.g. to reload certificates)
+    pub fn tls_provider(&self) -> Option<&TlsConfigProvider> {
+        self.listeners.first()?.tls_provider()
     }
 
     /// Read access to threads
@@ -103,30 +177,159 @@ impl HttpServer {
     }
 }
 
-/// Reads header and create HttpRequest to pass to Handler
-fn process_request(
+/// Parse HttpRequest and pass to Handler, returns response or error response with date header<br>
+/// Bodies of 1xx, 204 and 304 responses are removed
+pub(crate) fn handle_request(
+    server: &HttpServer,
+    raw_header: &str,
+    partial_body: Vec<u8>,
     stream: &mut impl ReadWrite,
-    address: SocketAddr,
-    settings: &HttpSettings,
-    handler: Handler,
-) -> Result<Vec<u8>> {
-    let (raw_header, partial_body) = read_header(stream, settings)?;
-    let request = HttpRequest::from(&raw_header, partial_body, stream, address, settings)?;
-    handler(request)
+    address: PeerAddr,
+    secure: bool,
+    #[cfg(feature = "tls")] client_certificate: Option<ClientCertificate>,
+) -> Vec<u8> {
+    let start = Instant::now();
+    let mut response = process_request(
+        server,
+        raw_header,
+        partial_body,
+        stream,
+        address,
+        secure,
+        #[cfg(feature = "tls")]
+        client_certificate,
+    );
+    insert_headers(&mut response, &[("date", &http_date_now())]);
+    strip_body(&mut response, false);
+
+    // record metrics
+    let method = raw_header.split(' ').next().unwrap_or_default();
+    if let Ok(method) = HttpMethod::try_from(method) {
+        let status = response_status(&response);
+        server
+            .metrics
+            .record_request(&method, status, start.elapsed());
+    }
+    server.metrics.record_bytes_out(response.len());
+    response
 }
 
-/// Accept connections
-fn accept_all(server: Arc<HttpServer>) {
+/// Parse HttpRequest and pass to Handler, returns response or error response
+fn process_request(
+    server: &HttpServer,
+    raw_header: &str,
+    partial_body: Vec<u8>,
+    stream: &mut impl ReadWrite,
+    address: PeerAddr,
+    secure: bool,
+    #[cfg(feature = "tls")] client_certificate: Option<ClientCertificate>,
+) -> Vec<u8> {
+    let start = Instant::now();
+    let mut request =
+        match HttpRequest::from(raw_header, partial_body, stream, address, server.settings()) {
+            Ok(request) => request,
+            Err(err) => {
+                return match err.downcast::<EarlyResponse>() {
+                    Ok(early_response) => early_response.0,
+                    Err(err) => {
+                        server.metrics.record_protocol_error();
+                        (server.error_handler)(ErrorContext::new(err, None))
+                    }
+                };
+            }
+        };
+    server
+        .metrics
+        .record_bytes_in(raw_header.len() + request.body().len());
+    request.set_secure(secure);
     #[cfg(feature = "tls")]
-    let tls_config = server.tls_config();
+    request.set_client_certificate(client_certificate);
+
+    // request context for HttpClient and hooks
+    let _context = enter_request(request.id());
+    if let Some(on_request_start) = server.settings.on_request_start {
+        on_request_start(&request);
+    }
+    let context = RequestContext {
+        id: request.id().to_string(),
+        method: *request.method(),
+        url: request.url().to_string(),
+        ip: request.ip().to_string(),
+    };
+
+    // handle and echo request id
+    let mut response = dispatch(server, request, &context);
+    insert_headers(&mut response, &[("x-request-id", &context.id)]);
+    if let Some(on_request_end) = server.settings.on_request_end {
+        on_request_end(&RequestSummary {
+            status: response_status(&response),
+            duration: start.elapsed(),
+            id: context.id,
+            method: context.method,
+            url: context.url,
+            ip: context.ip,
+        });
+    }
+    response
+}
+
+/// Answer CORS preflight, check authentication and pass to Handler
+fn dispatch(server: &HttpServer, mut request: HttpRequest, context: &RequestContext) -> Vec<u8> {
+    // answer CORS preflight
+    let cors = server.settings.cors.as_ref();
+    if let Some(response) = cors.and_then(|cors| cors.preflight(&request)) {
+        return response;
+    }
+    let origin = request
+        .headers()
+        .get("origin")
+        .map(|origin| origin.to_string());
+
+    // handle HEAD as GET
+    request.head_as_get();
+    let head = request.head();
 
+    // check authentication, handle request and add CORS headers
+    let auth = server.settings.auth.as_ref();
+    let metrics_path = server.settings.metrics_path.as_deref();
+    let mut response = match auth.and_then(|auth| auth.check(&request)) {
+        Some(response) => response,
+        None if metrics_path == Some(request.url()) => {
+            respond(server.metrics.render(), "text/plain; version=0.0.4", None)
+        }
+        None => (server.handler)(request).unwrap_or_else(|err| {
+            server.metrics.record_handler_error();
+            (server.error_handler)(ErrorContext::new(err, Some(context.clone())))
+        }),
+    };
+    if let (Some(cors), Some(origin)) = (cors, origin) {
+        cors.decorate(&origin, &mut response);
+    }
+
+    // headers only for HEAD
+    if head {
+        strip_body(&mut response, true);
+    }
+    response
+}
+
+/// Get status code of raw HTTP response (0 if invalid)
+fn response_status(response: &[u8]) -> u16 {
+    split_response(response)
+        .and_then(|(status, _, _)| status.get(..3)?.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Accept connections of listener
+fn accept_all(server: Arc<HttpServer>, index: usize) {
     loop {
         // accept connection
-        if let Ok((stream, address)) = server.listener.read().unwrap().accept() {
+        let listener = &server.listeners[index];
+        if let Ok((stream, address)) = listener.listener().accept() {
             // clones
-            let server = server.clone();
             #[cfg(feature = "tls")]
-            let tls_config = tls_config.clone();
+            let tls_config = listener.alpn_tls_config(server.settings.http2);
+            let server = server.clone();
 
             // spawn new thread
             use super::HttpThreads::{CONSTANT, SPAWN};
@@ -160,34 +363,95 @@ fn accept_all(server: Arc<HttpServer>) {
 
 fn accepted(
     server: &HttpServer,
-    mut stream: TcpStream,
-    address: SocketAddr,
+    mut stream: Connection,
+    address: PeerAddr,
     #[cfg(feature = "tls")] tls_config: Option<TlsConfig>,
 ) -> Result<()> {
+    // count active connection
+    let _connection = server.metrics.connection();
+
     // set timeouts
     stream.set_read_timeout(server.settings.read_timeout)?;
     stream.set_write_timeout(server.settings.write_timeout)?;
 
     // create TLS connection
     #[cfg(feature = "tls")]
+    let secure = tls_config.is_some();
+    #[cfg(not(feature = "tls"))]
+    let secure = false;
+    #[cfg(feature = "tls")]
     let mut session;
     #[cfg(feature = "tls")]
+    let mut client_certificate = None;
+    #[cfg(feature = "tls")]
+    let mut http2 = false;
+    #[cfg(feature = "tls")]
     let mut stream: Box<dyn ReadWrite> = match tls_config.clone() {
         Some(tls_config) => {
             session = ServerConnection::new(tls_config)
                 .or_else(|_| Fail::from("could not initialize server connection"))?;
+
+            // complete handshake to get client certificate
+            while session.is_handshaking() {
+                if let Err(err) = session.complete_io(&mut stream) {
+                    server.metrics.record_tls_handshake_failure();
+                    return Err(err.into());
+                }
+            }
+            if let Some(chain) = session.peer_certificates() {
+                client_certificate = Some(ClientCertificate::from_chain(chain)?);
+            }
+            http2 = session.alpn_protocol() == Some(b"h2");
+
             Box::new(RustlsStream::new(&mut session, &mut stream))
         }
         None => Box::new(stream),
     };
 
+    // HTTP/2 negotiated using ALPN
+    #[cfg(feature = "tls")]
+    if http2 {
+        return Http2Connection::new(&mut stream, server, address, Vec::new(), client_certificate)
+            .serve();
+    }
+
     // process request
-    let response = match process_request(&mut stream, address, server.settings(), server.handler) {
-        Ok(response) => response,
-        Err(err) => (server.error_handler)(err),
+    let mut response = match read_header(&mut stream, server.settings()) {
+        // HTTP/2 with prior knowledge (h2c)
+        Ok((raw_header, partial_body))
+            if server.settings.http2 && raw_header.starts_with("PRI * HTTP/2.0\r\n") =>
+        {
+            let buffered = [raw_header.as_bytes(), &partial_body].concat();
+            return Http2Connection::new(
+                &mut stream,
+                server,
+                address,
+                buffered,
+                #[cfg(feature = "tls")]
+                client_certificate,
+            )
+            .serve();
+        }
+        Ok((raw_header, partial_body)) => handle_request(
+            server,
+            &raw_header,
+            partial_body,
+            &mut stream,
+            address,
+            secure,
+            #[cfg(feature = "tls")]
+            client_certificate,
+        ),
+        // closed without sending anything (e.g. TCP health checks)
+        Err(err) if is_closed(err.as_ref()) => return Ok(()),
+        Err(err) => {
+            server.metrics.record_protocol_error();
+            (server.error_handler)(ErrorContext::new(err, None))
+        }
     };
 
-    // respond
+    // respond, connection is not kept alive
+    insert_headers(&mut response, &[("connection", "close")]);
     stream.write_all(&response)?;
     stream.flush().or_else(Fail::from)
 }
@@ -207,8 +471,11 @@ fn read_header(
     'l: loop {
         // read from stream and check max header size
         let length = stream.read(&mut buf)?;
+        if length == 0 && header.is_empty() {
+            return Err(IoError::from(ErrorKind::UnexpectedEof).into());
+        }
         if header.len() + length > http_settings.max_header_size {
-            return Fail::from("Max header size exceeded");
+            return Err(HttpError::header_too_large("Max header size exceeded"));
         }
 
         // only use actually read data
@@ -263,7 +530,7 @@ fn read_header(
 
             // failed too often
             if read_fails > http_settings.header_read_attempts {
-                return Fail::from("Read header failed too often");
+                return Err(HttpError::bad_request("Read header failed too often"));
             }
         }
     }
@@ -272,8 +539,14 @@ fn read_header(
     Ok((
         match String::from_utf8(header) {
             Ok(header) => header,
-            Err(err) => return Fail::from(err),
+            Err(_) => return Err(HttpError::bad_request("Header is not valid UTF-8")),
         },
         rest,
     ))
 }
+
+/// Whether connection was closed before a request was received
+fn is_closed(err: &(dyn Error + 'static)) -> bool {
+    err.downcast_ref::<IoError>()
+        .is_some_and(|err| err.kind() == ErrorKind::UnexpectedEof)
+}
@@ -2,6 +2,8 @@ use std::num::NonZeroUsize;
 use std::thread::available_parallelism;
 use std::time::Duration;
 
+use super::{AuthPolicy, ContinueHandler, CorsPolicy, EndHook, StartHook};
+
 /// HTTP server settings
 #[derive(Clone, Debug)]
 pub struct HttpSettings {
@@ -14,6 +16,13 @@ pub struct HttpSettings {
     pub read_timeout: Option<Duration>,
     pub write_timeout: Option<Duration>,
     pub threads: HttpThreads,
+    pub http2: bool,
+    pub cors: Option<CorsPolicy>,
+    pub auth: Option<AuthPolicy>,
+    pub continue_handler: Option<ContinueHandler>,
+    pub metrics_path: Option<String>,
+    pub on_request_start: Option<StartHook>,
+    pub on_request_end: Option<EndHook>,
 }
 
 impl Default for HttpSettings {
@@ -35,6 +44,13 @@ impl HttpSettings {
             read_timeout: Some(Duration::from_secs(10)),
             write_timeout: Some(Duration::from_secs(10)),
             threads: HttpThreads::SPAWN(available_parallelism().unwrap_or(NonZeroUsize::MIN).get()),
+            http2: false,
+            cors: None,
+            auth: None,
+            continue_handler: None,
+            metrics_path: None,
+            on_request_start: None,
+            on_request_end: None,
         }
     }
 
@@ -83,6 +99,48 @@ impl HttpSettings {
         self
     }
 
+    /// Enable or disable HTTP/2 (ALPN with TLS, prior knowledge without, disabled by default)
+    pub fn http2(mut self, http2: bool) -> Self {
+        self.http2 = http2;
+        self
+    }
+
+    /// Set CORS policy (preflight requests answered automatically when Some)
+    pub fn cors(mut self, cors: Option<CorsPolicy>) -> Self {
+        self.cors = cors;
+        self
+    }
+
+    /// Set authentication policy (unauthorized requests answered with 401 when Some)
+    pub fn auth(mut self, auth: Option<AuthPolicy>) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Set handler deciding on Expect: 100-continue (100 Continue sent if None)
+    pub fn continue_handler(mut self, continue_handler: Option<ContinueHandler>) -> Self {
+        self.continue_handler = continue_handler;
+        self
+    }
+
+    /// Set path serving metrics in Prometheus text format (e.g. Some("/metrics"))
+    pub fn metrics_path(mut self, metrics_path: Option<&str>) -> Self {
+        self.metrics_path = metrics_path.map(|metrics_path| metrics_path.to_string());
+        self
+    }
+
+    /// Set hook called before a request is handled
+    pub fn on_request_start(mut self, on_request_start: Option<StartHook>) -> Self {
+        self.on_request_start = on_request_start;
+        self
+    }
+
+    /// Set hook called after a request was handled (e.g. for access logs)
+    pub fn on_request_end(mut self, on_request_end: Option<EndHook>) -> Self {
+        self.on_request_end = on_request_end;
+        self
+    }
+
     pub fn threads_num(mut self, threads_num: usize) -> Self {
         use HttpThreads::{CONSTANT, SPAWN};
         match self.threads {
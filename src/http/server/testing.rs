@@ -0,0 +1,219 @@
+//! In-process testing of handlers without sockets
+
+use std::io::Cursor;
+use std::net::SocketAddr;
+use std::str;
+use std::sync::Arc;
+
+use crate::byte::scan;
+use crate::{Fail, Result};
+
+use super::{
+    ErrorHandler, Handler, HttpMethod, HttpRequest, HttpServer, HttpSettings, Metrics, PeerAddr,
+    default_error_handler, handle_request, split_response,
+};
+
+#[cfg(feature = "tls")]
+use super::ClientCertificate;
+
+/// Request to run through a handler or TestServer
+#[derive(Clone, Debug)]
+pub struct TestRequest {
+    header: String,
+    body: Vec<u8>,
+    peer_addr: PeerAddr,
+    #[cfg(feature = "tls")]
+    client_certificate: Option<ClientCertificate>,
+}
+
+impl TestRequest {
+    /// Create new TestRequest with method and URL (including GET parameters)
+    pub fn new(method: HttpMethod, url: impl AsRef<str>) -> Self {
+        Self {
+            header: format!("{} {} HTTP/1.1\r\n", method.as_str(), url.as_ref()),
+            body: Vec::new(),
+            peer_addr: SocketAddr::from(([127, 0, 0, 1], 0)).into(),
+            #[cfg(feature = "tls")]
+            client_certificate: None,
+        }
+    }
+
+    /// Create new GET TestRequest
+    pub fn get(url: impl AsRef<str>) -> Self {
+        Self::new(HttpMethod::Get, url)
+    }
+
+    /// Create new POST TestRequest
+    pub fn post(url: impl AsRef<str>) -> Self {
+        Self::new(HttpMethod::Post, url)
+    }
+
+    /// Create TestRequest from raw HTTP request bytes (header and body)
+    pub fn raw(raw: impl AsRef<[u8]>) -> Result<Self> {
+        // split header and body
+        let raw = raw.as_ref();
+        let header_end = scan(raw, b"\r\n\r\n").ok_or_else(|| Fail::new("no header end"))?;
+        let header = str::from_utf8(&raw[..header_end + 2])?.to_string();
+        let mut request = Self::get("/");
+        request.header = header;
+        request.body = raw[header_end + 4..].to_vec();
+        Ok(request)
+    }
+
+    /// Add header
+    pub fn header(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.header
+            .push_str(&format!("{}: {}\r\n", name.as_ref(), value.as_ref()));
+        self
+    }
+
+    /// Set body and content-length header
+    pub fn body(mut self, body: impl AsRef<[u8]>) -> Self {
+        self.body = body.as_ref().to_vec();
+        let content_length = self.body.len().to_string();
+        self.header("content-length", content_length)
+    }
+
+    /// Set address of peer (default 127.0.0.1:0)
+    pub fn peer_addr(mut self, peer_addr: impl Into<PeerAddr>) -> Self {
+        self.peer_addr = peer_addr.into();
+        self
+    }
+
+    #[cfg(feature = "tls")]
+    /// Set verified client certificate
+    pub fn client_certificate(mut self, client_certificate: ClientCertificate) -> Self {
+        self.client_certificate = Some(client_certificate);
+        self
+    }
+
+    /// Get raw HTTP request bytes
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut raw = format!("{}\r\n", self.header).into_bytes();
+        raw.extend_from_slice(&self.body);
+        raw
+    }
+
+    /// Run through handler only (no CORS, authentication or HEAD handling)
+    pub fn run(&self, handler: Handler, settings: &HttpSettings) -> Result<TestResponse> {
+        let mut stream = Cursor::new(Vec::new());
+        let request = HttpRequest::from(
+            &self.header,
+            self.body.clone(),
+            &mut stream,
+            self.peer_addr.clone(),
+            settings,
+        )?;
+        #[cfg(feature = "tls")]
+        let mut request = request;
+        #[cfg(feature = "tls")]
+        request.set_client_certificate(self.client_certificate.clone());
+        TestResponse::parse(&handler(request)?)
+    }
+}
+
+/// HttpServer running requests through its full stack without listeners
+#[derive(Debug)]
+pub struct TestServer {
+    server: HttpServer,
+}
+
+impl TestServer {
+    /// Create new TestServer with default settings and error handler
+    pub fn new(handler: Handler) -> Self {
+        Self::with_settings(HttpSettings::default(), handler, default_error_handler)
+    }
+
+    /// Create new TestServer
+    pub fn with_settings(
+        settings: HttpSettings,
+        handler: Handler,
+        error_handler: ErrorHandler,
+    ) -> Self {
+        Self {
+            server: HttpServer::detached(Arc::new(settings), handler, error_handler),
+        }
+    }
+
+    /// Get Metrics
+    pub fn metrics(&self) -> &Metrics {
+        self.server.metrics()
+    }
+
+    /// Run request (responses of 100 Continue are not included)
+    pub fn run(&self, request: &TestRequest) -> Result<TestResponse> {
+        let mut stream = Cursor::new(Vec::new());
+        let response = handle_request(
+            &self.server,
+            &request.header,
+            request.body.clone(),
+            &mut stream,
+            request.peer_addr.clone(),
+            false,
+            #[cfg(feature = "tls")]
+            request.client_certificate.clone(),
+        );
+        TestResponse::parse(&response)
+    }
+}
+
+/// Parsed HTTP response
+#[derive(Clone, Debug)]
+pub struct TestResponse {
+    status: u16,
+    reason: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl TestResponse {
+    /// Parse raw HTTP response
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let (status, headers, body) =
+            split_response(raw).ok_or_else(|| Fail::new("invalid HTTP response"))?;
+        let (code, reason) = status.split_once(' ').unwrap_or((status, ""));
+        Ok(Self {
+            status: code.parse()?,
+            reason: reason.to_string(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: body.to_vec(),
+        })
+    }
+
+    /// Get status code
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Get reason phrase (e.g. "Not Found")
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// Get headers in order of appearance
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Get first value of header (case-insensitive)
+    pub fn header(&self, name: impl AsRef<str>) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name.as_ref()))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get body as sent (respond appends \r\n)
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Get body as UTF-8 text without trailing \r\n of respond
+    pub fn text(&self) -> String {
+        let body = self.body.strip_suffix(b"\r\n").unwrap_or(&self.body);
+        String::from_utf8_lossy(body).to_string()
+    }
+}
@@ -1,59 +1,249 @@
 //! TLS utils
 
-use crate::{Fail, Result};
+use crate::{Error, Fail, Result};
 
-use rustls::server::ServerConfig;
+use rustls::RootCertStore;
+use rustls::server::{ServerConfig, WebPkiClientVerifier};
 use rustls_pemfile::Item::{Pkcs1Key, Pkcs8Key, Sec1Key};
 use rustls_pemfile::{certs, read_one};
 use rustls_pki_types::{CertificateDer, PrivateKeyDer};
-use std::fs::File;
+use std::fs::{File, metadata};
 use std::io::BufReader;
 use std::io::prelude::*;
-use std::sync::{Arc, OnceLock};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::thread::{JoinHandle, sleep, spawn};
+use std::time::{Duration, SystemTime};
 
 pub type TlsConfig = Arc<ServerConfig>;
-pub type TlsConfigProvider = fn() -> TlsConfig;
 
-static TLS_CONFIG_LC: OnceLock<TlsConfig> = OnceLock::new();
-static TLS_CONFIG_CC: OnceLock<TlsConfig> = OnceLock::new();
+/// Called with the error of a failed reload while watching (old TlsConfig is kept)
+pub type ReloadErrorHandler = fn(Error);
 
-/// Initializes and returns a single TlsConfigProvider (using load_certificate)<br>
-/// The first call sets the TlsConfig, any following call will only return the same provider as the first call!
+/// Reloadable TLS configuration handle<br>
+/// Clones share the same configuration, so a reload is seen by every clone (and the server)
+#[derive(Clone, Debug)]
+pub struct TlsConfigProvider {
+    config: Arc<RwLock<TlsConfig>>,
+    source: Arc<TlsSource>,
+}
+
+/// Origin of a TlsConfig, used for reloading
+#[derive(Debug)]
+enum TlsSource {
+    Static,
+    Files {
+        cert_path: String,
+        key_path: String,
+        client_auth: ClientAuth,
+        modified: Mutex<Option<(SystemTime, SystemTime)>>,
+    },
+}
+
+/// Client certificate authentication (mutual TLS)
+#[derive(Clone, Debug, Default)]
+pub enum ClientAuth {
+    /// Don't request client certificates
+    #[default]
+    Off,
+
+    /// Request a client certificate signed by a trusted CA, but allow clients without one
+    Optional(Arc<RootCertStore>),
+
+    /// Require a client certificate signed by a trusted CA
+    Required(Arc<RootCertStore>),
+}
+
+impl TlsConfigProvider {
+    /// Create provider with a fixed TlsConfig (can still be replaced using set)
+    pub fn new(tls_config: TlsConfig) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(tls_config)),
+            source: Arc::new(TlsSource::Static),
+        }
+    }
+
+    /// Create provider from certificate and private key file (reloadable)
+    pub fn from_files(cert_path: impl AsRef<str>, key_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_files_with_client_auth(cert_path, key_path, ClientAuth::Off)
+    }
+
+    /// Create provider from certificate and private key file with client authentication (reloadable)
+    pub fn from_files_with_client_auth(
+        cert_path: impl AsRef<str>,
+        key_path: impl AsRef<str>,
+        client_auth: ClientAuth,
+    ) -> Result<Self> {
+        let cert_path = cert_path.as_ref().to_string();
+        let key_path = key_path.as_ref().to_string();
+        let modified = modified_times(&cert_path, &key_path).ok();
+        let tls_config = load_certificate_with_client_auth(&cert_path, &key_path, &client_auth)?;
+        Ok(Self {
+            config: Arc::new(RwLock::new(tls_config)),
+            source: Arc::new(TlsSource::Files {
+                cert_path,
+                key_path,
+                client_auth,
+                modified: Mutex::new(modified),
+            }),
+        })
+    }
+
+    /// Get the current TlsConfig
+    pub fn config(&self) -> TlsConfig {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Replace the current TlsConfig<br>
+    /// Existing connections keep using the TlsConfig they were created with
+    pub fn set(&self, tls_config: TlsConfig) {
+        *self.config.write().unwrap() = tls_config;
+    }
+
+    /// Reload certificate and private key from files<br>
+    /// The current TlsConfig is kept if loading fails
+    pub fn reload(&self) -> Result<()> {
+        match self.source.as_ref() {
+            TlsSource::Static => Fail::from("TLS configuration was not loaded from files"),
+            TlsSource::Files {
+                cert_path,
+                key_path,
+                client_auth,
+                modified,
+            } => {
+                let new_modified = modified_times(cert_path, key_path).ok();
+                self.set(load_certificate_with_client_auth(
+                    cert_path,
+                    key_path,
+                    client_auth,
+                )?);
+                *modified.lock().or_else(Fail::from)? = new_modified;
+                Ok(())
+            }
+        }
+    }
+
+    /// Reload certificate and private key if one of the files was modified<br>
+    /// Returns whether the TlsConfig was reloaded
+    pub fn reload_if_modified(&self) -> Result<bool> {
+        match self.source.as_ref() {
+            TlsSource::Static => Ok(false),
+            TlsSource::Files {
+                cert_path,
+                key_path,
+                modified,
+                ..
+            } => {
+                let new_modified = modified_times(cert_path, key_path)?;
+                if *modified.lock().or_else(Fail::from)? == Some(new_modified) {
+                    return Ok(false);
+                }
+                self.reload()?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Spawn thread polling certificate and private key files for modifications<br>
+    /// The thread stops when every clone of this provider has been dropped
+    pub fn watch(&self, interval: Duration, on_error: ReloadErrorHandler) -> JoinHandle<()> {
+        let config = Arc::downgrade(&self.config);
+        let source = Arc::downgrade(&self.source);
+        spawn(move || {
+            loop {
+                sleep(interval);
+
+                // stop if provider dropped
+                let provider = match (Weak::upgrade(&config), Weak::upgrade(&source)) {
+                    (Some(config), Some(source)) => Self { config, source },
+                    _ => break,
+                };
+
+                // reload and keep old config on error
+                if let Err(err) = provider.reload_if_modified() {
+                    on_error(err);
+                }
+            }
+        })
+    }
+}
+
+/// Get modification times of certificate and private key file
+fn modified_times(cert_path: &str, key_path: &str) -> Result<(SystemTime, SystemTime)> {
+    Ok((
+        metadata(cert_path)?.modified()?,
+        metadata(key_path)?.modified()?,
+    ))
+}
+
+/// Create a reloadable TlsConfigProvider (using load_certificate)
 pub fn load_certificate_provider(
     cert_path: impl AsRef<str>,
     key_path: impl AsRef<str>,
 ) -> Result<TlsConfigProvider> {
-    let tls_config = load_certificate(cert_path, key_path)?;
-    TLS_CONFIG_LC.get_or_init(|| tls_config);
-    Ok(provide_tls_config_lc)
+    TlsConfigProvider::from_files(cert_path, key_path)
 }
 
-/// Initializes and returns a single TlsConfigProvider (using certificate_config)<br>
-/// The first call sets the TlsConfig, any following call will only return the same provider as the first call!
+/// Create a TlsConfigProvider (using certificate_config)
 pub fn certificate_config_provider(
     raw_cert: impl AsRef<[u8]>,
     raw_key: impl AsRef<[u8]>,
 ) -> Result<TlsConfigProvider> {
-    let tls_config = certificate_config(raw_cert, raw_key)?;
-    TLS_CONFIG_CC.get_or_init(|| tls_config);
-    Ok(provide_tls_config_cc)
+    certificate_config(raw_cert, raw_key).map(TlsConfigProvider::new)
 }
 
-fn provide_tls_config_lc() -> TlsConfig {
-    TLS_CONFIG_LC.get().unwrap().clone()
+/// Parse trusted CA certificates for client authentication
+pub fn client_ca(raw_ca: impl AsRef<[u8]>) -> Result<Arc<RootCertStore>> {
+    // open certificates
+    let mut ca_buf = BufReader::new(raw_ca.as_ref());
+    let mut roots = RootCertStore::empty();
+    for cert in certs(&mut ca_buf) {
+        let cert = cert.or_else(|_| Fail::from("broken CA certificate"))?;
+        roots.add(cert).or_else(Fail::from)?;
+    }
+
+    // check if any CA certificate found
+    if roots.is_empty() {
+        return Fail::from("no CA certificate found");
+    }
+    Ok(Arc::new(roots))
 }
 
-fn provide_tls_config_cc() -> TlsConfig {
-    TLS_CONFIG_CC.get().unwrap().clone()
+/// Parse trusted CA certificates for client authentication from file
+pub fn load_client_ca(ca_path: impl AsRef<str>) -> Result<Arc<RootCertStore>> {
+    let mut ca_buf = Vec::new();
+    File::open(ca_path.as_ref())?.read_to_end(&mut ca_buf)?;
+    client_ca(&ca_buf)
 }
 
 /// Generate config with TLS certificate and private key
 pub fn certificate_config(
     raw_cert: impl AsRef<[u8]>,
     raw_key: impl AsRef<[u8]>,
+) -> Result<TlsConfig> {
+    certificate_config_with_client_auth(raw_cert, raw_key, &ClientAuth::Off)
+}
+
+/// Generate config with TLS certificate, private key and client authentication
+pub fn certificate_config_with_client_auth(
+    raw_cert: impl AsRef<[u8]>,
+    raw_key: impl AsRef<[u8]>,
+    client_auth: &ClientAuth,
 ) -> Result<TlsConfig> {
     // create config
-    let config = ServerConfig::builder().with_no_client_auth();
+    let config = match client_auth {
+        ClientAuth::Off => ServerConfig::builder().with_no_client_auth(),
+        ClientAuth::Optional(roots) => ServerConfig::builder().with_client_cert_verifier(
+            WebPkiClientVerifier::builder(roots.clone())
+                .allow_unauthenticated()
+                .build()
+                .or_else(Fail::from)?,
+        ),
+        ClientAuth::Required(roots) => ServerConfig::builder().with_client_cert_verifier(
+            WebPkiClientVerifier::builder(roots.clone())
+                .build()
+                .or_else(Fail::from)?,
+        ),
+    };
 
     // open certificate
     let mut cert_buf = BufReader::new(raw_cert.as_ref());
@@ -82,6 +272,15 @@ pub fn certificate_config(
 pub fn load_certificate(
     cert_path: impl AsRef<str>,
     key_path: impl AsRef<str>,
+) -> Result<TlsConfig> {
+    load_certificate_with_client_auth(cert_path, key_path, &ClientAuth::Off)
+}
+
+/// Generate config with TLS certificate, private key and client authentication from file
+pub fn load_certificate_with_client_auth(
+    cert_path: impl AsRef<str>,
+    key_path: impl AsRef<str>,
+    client_auth: &ClientAuth,
 ) -> Result<TlsConfig> {
     // open files
     let mut cert_file = File::open(cert_path.as_ref())?;
@@ -96,5 +295,5 @@ pub fn load_certificate(
     key_file.read_to_end(&mut key_buf)?;
 
     // generate config and return
-    certificate_config(&cert_buf, &key_buf)
+    certificate_config_with_client_auth(&cert_buf, &key_buf, client_auth)
 }
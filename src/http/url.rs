@@ -0,0 +1,549 @@
+//! URL parsing, normalisation and resolution (RFC 3986)
+
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::net::Ipv6Addr;
+use std::str::FromStr;
+
+use crate::{Fail, Result};
+
+/// Absolute URL, normalised on parsing (RFC 3986 section 6.2.2)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Url {
+    scheme: String,
+    userinfo: Option<String>,
+    host: Option<String>,
+    port: Option<u16>,
+    path: String,
+    query: Option<String>,
+    fragment: Option<String>,
+}
+
+/// Components of URI reference, not yet validated
+struct Reference<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: &'a str,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+impl Url {
+    /// Parse absolute URL (e.g. "https://user@[::1]:8080/a/../b?q=1#top")
+    pub fn parse(url: &str) -> Result<Self> {
+        let reference = Reference::split(url.trim());
+        let Some(scheme) = reference.scheme else {
+            return Fail::from("invalid URL: scheme missing");
+        };
+
+        // authority: [userinfo@]host[:port]
+        let (userinfo, host, port) = match reference.authority {
+            Some(authority) => {
+                let (userinfo, host_port) = match authority.rsplit_once('@') {
+                    Some((userinfo, host_port)) => (Some(userinfo), host_port),
+                    None => (None, authority),
+                };
+                let (host, port) = parse_host_port(host_port)?;
+                (
+                    userinfo.map(|userinfo| normalize(userinfo, is_userinfo)),
+                    Some(host),
+                    port,
+                )
+            }
+            None => (None, None, None),
+        };
+
+        let mut url = Self {
+            scheme: String::new(),
+            userinfo,
+            host,
+            port,
+            path: String::new(),
+            query: None,
+            fragment: None,
+        };
+        url.set_scheme(scheme)?;
+        if url.host.as_deref().is_none_or(str::is_empty) && url.default_port().is_some() {
+            return Fail::from("invalid URL: host missing");
+        }
+        url.set_path(reference.path);
+        url.set_query(reference.query);
+        url.set_fragment(reference.fragment);
+        Ok(url)
+    }
+
+    /// Resolve reference (e.g. "../other?x") against this URL (RFC 3986 section 5.2)
+    pub fn join(&self, reference: &str) -> Result<Self> {
+        let reference = Reference::split(reference.trim());
+        if reference.scheme.is_some() {
+            return Self::parse(&reference.to_string());
+        }
+
+        // authority and path of reference or base
+        let mut target = Reference {
+            scheme: Some(&self.scheme),
+            authority: reference.authority,
+            path: reference.path,
+            query: reference.query,
+            fragment: reference.fragment,
+        };
+        let authority = self.authority();
+        let merged;
+        if reference.authority.is_none() {
+            target.authority = authority.as_deref();
+            if reference.path.is_empty() {
+                target.path = &self.path;
+                target.query = reference.query.or(self.query.as_deref());
+            } else if !reference.path.starts_with('/') {
+                let directory = match self.path.rfind('/') {
+                    Some(index) => &self.path[..=index],
+                    None if self.host.is_some() => "/",
+                    None => "",
+                };
+                merged = format!("{directory}{}", reference.path);
+                target.path = &merged;
+            }
+        }
+        Self::parse(&target.to_string())
+    }
+
+    /// Get lowercase scheme (e.g. "https")
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Get percent-encoded user name, empty if none
+    pub fn username(&self) -> &str {
+        let userinfo = self.userinfo.as_deref().unwrap_or_default();
+        userinfo.split(':').next().unwrap_or_default()
+    }
+
+    /// Get percent-encoded password
+    pub fn password(&self) -> Option<&str> {
+        Some(self.userinfo.as_deref()?.split_once(':')?.1)
+    }
+
+    /// Get lowercase host, IPv6 addresses in brackets (e.g. "[::1]")
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// Get port, None if absent or default port of scheme
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// Get port or default port of scheme
+    pub fn port_or_default(&self) -> Option<u16> {
+        self.port.or(self.default_port())
+    }
+
+    /// Get percent-encoded path without dot segments
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Get percent-encoded query without "?"
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// Get percent-encoded fragment without "#"
+    pub fn fragment(&self) -> Option<&str> {
+        self.fragment.as_deref()
+    }
+
+    /// Get decoded name-value pairs of query ("+" is a space)
+    pub fn query_pairs(&self) -> Vec<(String, String)> {
+        let query = self.query.as_deref().unwrap_or_default();
+        query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode_query(name), decode_query(value))
+            })
+            .collect()
+    }
+
+    /// Get decoded value of first query pair with name
+    pub fn query_value(&self, name: &str) -> Option<String> {
+        self.query_pairs()
+            .into_iter()
+            .find_map(|(pair_name, value)| (pair_name == name).then_some(value))
+    }
+
+    /// Get host and port as sent in Host header (e.g. "example.com:8080")
+    pub fn host_port(&self) -> Option<String> {
+        let host = self.host.as_deref()?;
+        Some(match self.port {
+            Some(port) => format!("{host}:{port}"),
+            None => host.to_string(),
+        })
+    }
+
+    /// Get path and query as sent in request line (e.g. "/search?q=kern")
+    pub fn request_target(&self) -> String {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        match &self.query {
+            Some(query) => format!("{path}?{query}"),
+            None => path.to_string(),
+        }
+    }
+
+    /// Change scheme, port is dropped if it is the new default port
+    pub fn set_scheme(&mut self, scheme: &str) -> Result<()> {
+        let mut chars = scheme.chars();
+        if !chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+            || !chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        {
+            return Fail::from("invalid URL: invalid scheme");
+        }
+        self.scheme = scheme.to_ascii_lowercase();
+        if self.port == self.default_port() {
+            self.port = None;
+        }
+        Ok(())
+    }
+
+    /// Change path, characters not allowed in paths are percent-encoded
+    pub fn set_path(&mut self, path: &str) {
+        let path = remove_dot_segments(&normalize(path, is_path));
+        self.path = match self.host.is_some() && !path.starts_with('/') {
+            true => format!("/{path}"),
+            false => path,
+        };
+    }
+
+    /// Change query, characters not allowed in queries are percent-encoded
+    pub fn set_query(&mut self, query: Option<&str>) {
+        self.query = query.map(|query| normalize(query, is_query));
+    }
+
+    /// Append name-value pair to query, both are percent-encoded
+    pub fn append_query_pair(&mut self, name: &str, value: &str) {
+        let pair = format!("{}={}", encode(name), encode(value));
+        self.query = Some(match self.query.take().filter(|query| !query.is_empty()) {
+            Some(query) => format!("{query}&{pair}"),
+            None => pair,
+        });
+    }
+
+    /// Remove query pairs with decoded name
+    pub fn remove_query_pairs(&mut self, name: &str) {
+        let query = self.query.take().unwrap_or_default();
+        let pairs: Vec<&str> = query
+            .split('&')
+            .filter(|pair| {
+                let pair_name = pair.split('=').next().unwrap_or_default();
+                !pair.is_empty() && decode_query(pair_name) != name
+            })
+            .collect();
+        self.query = (!pairs.is_empty()).then(|| pairs.join("&"));
+    }
+
+    /// Change fragment, characters not allowed in fragments are percent-encoded
+    pub fn set_fragment(&mut self, fragment: Option<&str>) {
+        self.fragment = fragment.map(|fragment| normalize(fragment, is_query));
+    }
+
+    /// Whether scheme is https or wss
+    pub(crate) fn is_secure(&self) -> bool {
+        matches!(self.scheme.as_str(), "https" | "wss")
+    }
+
+    /// Get host without IPv6 brackets for name resolution and TLS
+    pub(crate) fn hostname(&self) -> &str {
+        let host = self.host.as_deref().unwrap_or_default();
+        host.strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .unwrap_or(host)
+    }
+
+    /// Get host and port to connect to (e.g. "[::1]:443")
+    pub(crate) fn socket_addr(&self) -> String {
+        let host = self.host.as_deref().unwrap_or_default();
+        format!("{host}:{}", self.port_or_default().unwrap_or_default())
+    }
+
+    /// Get [userinfo@]host[:port]
+    fn authority(&self) -> Option<String> {
+        let host_port = self.host_port()?;
+        Some(match &self.userinfo {
+            Some(userinfo) => format!("{userinfo}@{host_port}"),
+            None => host_port,
+        })
+    }
+
+    /// Default port of scheme
+    fn default_port(&self) -> Option<u16> {
+        match self.scheme.as_str() {
+            "http" | "ws" => Some(80),
+            "https" | "wss" => Some(443),
+            "ftp" => Some(21),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Url {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let authority = self.authority();
+        let reference = Reference {
+            scheme: Some(&self.scheme),
+            authority: authority.as_deref(),
+            path: &self.path,
+            query: self.query.as_deref(),
+            fragment: self.fragment.as_deref(),
+        };
+        write!(f, "{reference}")
+    }
+}
+
+impl FromStr for Url {
+    type Err = crate::Error;
+
+    fn from_str(url: &str) -> Result<Self> {
+        Self::parse(url)
+    }
+}
+
+impl TryFrom<&str> for Url {
+    type Error = crate::Error;
+
+    fn try_from(url: &str) -> Result<Self> {
+        Self::parse(url)
+    }
+}
+
+impl<'a> Reference<'a> {
+    /// Split URI reference into components (RFC 3986 appendix B)
+    fn split(reference: &'a str) -> Self {
+        let (rest, fragment) = match reference.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (reference, None),
+        };
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, Some(query)),
+            None => (rest, None),
+        };
+
+        // scheme ends at first colon before any slash
+        let (scheme, rest) = match rest.split_once(':') {
+            Some((scheme, rest)) if !scheme.is_empty() && !scheme.contains('/') => {
+                (Some(scheme), rest)
+            }
+            _ => (None, rest),
+        };
+        let (authority, path) = match rest.strip_prefix("//") {
+            Some(rest) => {
+                let index = rest.find('/').unwrap_or(rest.len());
+                (Some(&rest[..index]), &rest[index..])
+            }
+            None => (None, rest),
+        };
+        Self {
+            scheme,
+            authority,
+            path,
+            query,
+            fragment,
+        }
+    }
+}
+
+impl Display for Reference<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if let Some(scheme) = self.scheme {
+            write!(f, "{scheme}:")?;
+        }
+        if let Some(authority) = self.authority {
+            write!(f, "//{authority}")?;
+        }
+        write!(f, "{}", self.path)?;
+        if let Some(query) = self.query {
+            write!(f, "?{query}")?;
+        }
+        if let Some(fragment) = self.fragment {
+            write!(f, "#{fragment}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse and normalise host and optional port of authority
+fn parse_host_port(host_port: &str) -> Result<(String, Option<u16>)> {
+    let (host, port) = match host_port.strip_prefix('[') {
+        // IPv6 literal in brackets
+        Some(rest) => {
+            let (address, port) = rest
+                .split_once(']')
+                .ok_or_else(|| Fail::new("invalid URL: unclosed IPv6 address"))?;
+            let address: Ipv6Addr = address
+                .parse()
+                .map_err(|_| Fail::new("invalid URL: invalid IPv6 address"))?;
+            let port = match port {
+                "" => None,
+                port => Some(
+                    port.strip_prefix(':')
+                        .ok_or_else(|| Fail::new("invalid URL: invalid port"))?,
+                ),
+            };
+            (format!("[{address}]"), port)
+        }
+
+        // registered name or IPv4 address
+        None => {
+            let (host, port) = match host_port.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host_port, None),
+            };
+            if !host
+                .bytes()
+                .all(|b| is_unreserved(b) || is_sub_delim(b) || b == b'%')
+            {
+                return Fail::from("invalid URL: invalid host");
+            }
+            (normalize(&host.to_ascii_lowercase(), is_reg_name), port)
+        }
+    };
+
+    // empty port is the same as none
+    let port = match port.filter(|port| !port.is_empty()) {
+        Some(port) if port.bytes().all(|b| b.is_ascii_digit()) => Some(
+            port.parse()
+                .map_err(|_| Fail::new("invalid URL: invalid port"))?,
+        ),
+        Some(_) => return Fail::from("invalid URL: invalid port"),
+        None => None,
+    };
+    Ok((host, port))
+}
+
+/// Remove "." and ".." segments of path (RFC 3986 section 5.2.4)
+fn remove_dot_segments(path: &str) -> String {
+    let mut input = path;
+    let mut output = String::with_capacity(path.len());
+    while !input.is_empty() {
+        if let Some(rest) = input
+            .strip_prefix("../")
+            .or_else(|| input.strip_prefix("./"))
+        {
+            input = rest;
+        } else if input.starts_with("/./") || input == "/." {
+            input = &input[2..];
+            if input.is_empty() {
+                input = "/";
+            }
+        } else if input.starts_with("/../") || input == "/.." {
+            input = &input[3..];
+            if input.is_empty() {
+                input = "/";
+            }
+            output.truncate(output.rfind('/').unwrap_or_default());
+        } else if input == "." || input == ".." {
+            input = "";
+        } else {
+            // move first segment with leading slash to output
+            let end = input[1..].find('/').map_or(input.len(), |index| index + 1);
+            output.push_str(&input[..end]);
+            input = &input[end..];
+        }
+    }
+    output
+}
+
+/// Percent-encode bytes not allowed, uppercase escapes and decode unreserved characters
+fn normalize(value: &str, allowed: fn(u8) -> bool) -> String {
+    let bytes = value.as_bytes();
+    let mut normalized = String::with_capacity(value.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        match (byte, decode_escape(&bytes[index..])) {
+            (b'%', Some(decoded)) if is_unreserved(decoded) => {
+                normalized.push(decoded as char);
+                index += 2;
+            }
+            (b'%', Some(decoded)) => {
+                normalized.push_str(&format!("%{decoded:02X}"));
+                index += 2;
+            }
+            (byte, _) if byte != b'%' && allowed(byte) => normalized.push(byte as char),
+            (byte, _) => normalized.push_str(&format!("%{byte:02X}")),
+        }
+        index += 1;
+    }
+    normalized
+}
+
+/// Percent-encode all but unreserved characters
+fn encode(value: &str) -> String {
+    normalize(&value.replace('%', "%25"), is_unreserved)
+}
+
+/// Decode percent-encoded bytes, invalid escapes are kept
+pub(crate) fn percent_decode(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match decode_escape(&bytes[index..]) {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// Decode query component with "+" as space
+fn decode_query(value: &str) -> String {
+    String::from_utf8_lossy(&percent_decode(&value.replace('+', " "))).to_string()
+}
+
+/// Decode escape at start of bytes (e.g. "%2F")
+fn decode_escape(bytes: &[u8]) -> Option<u8> {
+    match bytes {
+        [b'%', high, low, ..] if high.is_ascii_hexdigit() && low.is_ascii_hexdigit() => {
+            let hex = [*high, *low];
+            u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()
+        }
+        _ => None,
+    }
+}
+
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')
+}
+
+fn is_sub_delim(byte: u8) -> bool {
+    matches!(
+        byte,
+        b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'='
+    )
+}
+
+fn is_reg_name(byte: u8) -> bool {
+    is_unreserved(byte) || is_sub_delim(byte)
+}
+
+fn is_userinfo(byte: u8) -> bool {
+    is_reg_name(byte) || byte == b':'
+}
+
+fn is_path(byte: u8) -> bool {
+    is_userinfo(byte) || matches!(byte, b'@' | b'/')
+}
+
+fn is_query(byte: u8) -> bool {
+    is_path(byte) || byte == b'?'
+}
@@ -1,4 +1,4 @@
-use kern::byte::{scan, split, splitn};
+use kern::byte::{base64_decode, base64_encode, constant_time_eq, scan, split, splitn};
 
 #[test]
 fn test_splitn() {
@@ -33,3 +33,42 @@ fn test_scan() {
     v.reverse();
     assert_eq!(scan(&v, &[7, 6, 5]).unwrap(), 4);
 }
+
+#[test]
+fn test_base64() {
+    // RFC 4648 test vectors
+    let vectors = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+    for (plain, encoded) in vectors {
+        assert_eq!(base64_encode(plain), encoded);
+        assert_eq!(base64_decode(encoded).unwrap(), plain.as_bytes());
+        assert_eq!(
+            base64_decode(encoded.trim_end_matches('=')).unwrap(),
+            plain.as_bytes()
+        );
+    }
+    assert_eq!(
+        base64_decode(base64_encode([0xff, 0xfe, 0x00])).unwrap(),
+        [0xff, 0xfe, 0x00]
+    );
+
+    // invalid input
+    assert!(base64_decode("Zm9v!").is_err());
+    assert!(base64_decode("Z").is_err());
+    assert!(base64_decode("Zg===").is_err());
+    assert!(base64_decode("Zg=").is_err());
+}
+
+#[test]
+fn test_constant_time_eq() {
+    assert!(constant_time_eq("secret", "secret"));
+    assert!(!constant_time_eq("secret", "secreT"));
+    assert!(!constant_time_eq("secret", "secret1"));
+}
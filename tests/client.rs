@@ -0,0 +1,723 @@
+use kern::Result;
+use kern::http::client::{
+    CookieJar, HttpClient, MultipartForm, Proxy, ProxyConfig, RedirectPolicy, TimeoutError,
+    TimeoutKind, Timeouts,
+};
+use kern::http::server::{
+    HttpMethod, HttpRequest, HttpServer, HttpSettings, Listener, ResponseData, redirect, respond,
+};
+use std::io::BufReader;
+use std::io::prelude::*;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
+
+fn keep_alive_server() -> (SocketAddr, Arc<AtomicUsize>) {
+    // listen on random port and count connections
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let counter = connections.clone();
+    spawn(move || {
+        for stream in listener.incoming() {
+            let number = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            spawn(move || serve_connection(stream.unwrap(), number));
+        }
+    });
+    (address, connections)
+}
+
+fn serve_connection(mut stream: TcpStream, number: usize) {
+    // answer requests until client closes or asks to close
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    loop {
+        let mut connection = String::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).unwrap_or_default() == 0 {
+                return;
+            } else if line == "\r\n" {
+                break;
+            } else if let Some(value) = line.to_lowercase().strip_prefix("connection:") {
+                connection = value.trim().to_string();
+            }
+        }
+        let body = format!("{number} {connection}");
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+        if connection == "close" {
+            return;
+        }
+    }
+}
+
+fn scripted_server(response: &'static str, close: bool) -> SocketAddr {
+    // send response to first request, then close or wait for client to close
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 2 {
+            line.clear();
+        }
+        stream.write_all(response.as_bytes()).unwrap();
+        if !close {
+            reader.read_to_end(&mut Vec::new()).ok();
+        }
+    });
+    address
+}
+
+fn redirect_handler(req: HttpRequest) -> Result<Vec<u8>> {
+    // redirect by status or describe request
+    let port = req.headers()["host"].rsplit(':').next().unwrap_or_default();
+    let (status, location) = match req.url() {
+        "/a/see-other" => return Ok(redirect("../target")),
+        "/found" => ("302 Found", "/target".to_string()),
+        "/temporary" => ("307 Temporary Redirect", "target".to_string()),
+        "/loop" => ("308 Permanent Redirect", "/loop".to_string()),
+        "/cross-host" => ("302 Found", format!("http://localhost:{port}/target")),
+        _ => {
+            let authorization = req.headers().get("authorization").unwrap_or(&"-");
+            let body = format!(
+                "{:?} {} {authorization}",
+                req.method(),
+                String::from_utf8_lossy(req.body())
+            );
+            return Ok(respond(body, "text/plain", None));
+        }
+    };
+    let data = ResponseData::new()
+        .status(status)
+        .header("Location", &location);
+    Ok(respond("", "text/plain", data.build()))
+}
+
+fn proxy_server(status: &'static str) -> SocketAddr {
+    // answer first request with its request line and proxy credentials
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let (mut request_line, mut authorization, mut line) =
+            (String::new(), "-".to_string(), String::new());
+        reader.read_line(&mut request_line).unwrap();
+        while reader.read_line(&mut line).unwrap() > 2 {
+            if let Some(value) = line.strip_prefix("Proxy-Authorization: ") {
+                authorization = value.trim().to_string();
+            }
+            line.clear();
+        }
+        let body = format!("{} {authorization}", request_line.trim());
+        let response = format!(
+            "HTTP/1.1 {status}\r\ncontent-length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+    address
+}
+
+fn cookie_handler(req: HttpRequest) -> Result<Vec<u8>> {
+    // set cookie and redirect on login, otherwise echo cookies
+    if req.url() == "/login" {
+        let data = ResponseData::found()
+            .header("Location", "/account")
+            .header("Set-Cookie", "session=abc; Path=/; HttpOnly");
+        return Ok(respond("", "text/plain", data.build()));
+    }
+    let cookie = req.headers().get("cookie").unwrap_or(&"-");
+    Ok(respond(*cookie, "text/plain", None))
+}
+
+fn serve(handler: fn(HttpRequest) -> Result<Vec<u8>>) -> SocketAddr {
+    // listen on random port
+    let listener = Listener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    HttpServer::with_listener(
+        listener,
+        Arc::new(HttpSettings::new()),
+        handler,
+        |context| respond(context.error.to_string(), "text/plain", None),
+        #[cfg(feature = "tls")]
+        None,
+    )
+    .unwrap();
+    address
+}
+
+fn echo_server() -> SocketAddr {
+    // respond with framing and decoded body of first request
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let (mut framing, mut content_type, mut line) =
+            (String::new(), String::new(), String::new());
+        while reader.read_line(&mut line).unwrap() > 2 {
+            let lower = line.to_lowercase();
+            if lower.starts_with("content-length:") || lower.starts_with("transfer-encoding:") {
+                framing = lower.trim().to_string();
+            } else if lower.starts_with("content-type:") {
+                content_type = format!("{}\n", line.trim());
+            }
+            line.clear();
+        }
+        let mut body = Vec::new();
+        if framing == "transfer-encoding: chunked" {
+            loop {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+                let size = usize::from_str_radix(line.trim(), 16).unwrap();
+                let mut chunk = vec![0u8; size + 2];
+                reader.read_exact(&mut chunk).unwrap();
+                body.extend_from_slice(&chunk[..size]);
+                if size == 0 {
+                    break;
+                }
+            }
+        } else if let Some(length) = framing.strip_prefix("content-length: ") {
+            body = vec![0u8; length.parse().unwrap()];
+            if reader.read_exact(&mut body).is_err() {
+                return;
+            }
+        }
+        let body = format!(
+            "{framing}\n{content_type}{}",
+            String::from_utf8(body).unwrap()
+        );
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+    address
+}
+
+#[test]
+fn connection_pool() {
+    // connection is reused
+    let (address, connections) = keep_alive_server();
+    let client = HttpClient::new();
+    for _ in 0..3 {
+        let response = client.get(format!("http://{address}/")).unwrap();
+        assert_eq!(response.body_text().unwrap(), "1 keep-alive");
+    }
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+    assert_eq!(client.pool().idle(), 1);
+
+    // keep-alive disabled
+    let client = HttpClient::new().with_max_idle(0);
+    for number in 2..4 {
+        let response = client.get(format!("http://{address}/")).unwrap();
+        assert_eq!(response.body_text().unwrap(), format!("{number} close"));
+    }
+    assert_eq!(client.pool().idle(), 0);
+
+    // expired connections are closed
+    let client = HttpClient::new().with_idle_timeout(Duration::from_millis(50));
+    client.get(format!("http://{address}/")).unwrap();
+    sleep(Duration::from_millis(100));
+    let response = client.get(format!("http://{address}/")).unwrap();
+    assert_eq!(response.body_text().unwrap(), "5 keep-alive");
+
+    // connections closed by server are not reused
+    let address = serve(|req| Ok(respond(req.url(), "text/plain", None)));
+    let client = HttpClient::new();
+    for path in ["/first", "/second", "/third"] {
+        let response = client.get(format!("http://{address}{path}")).unwrap();
+        assert_eq!(response.body_text().unwrap(), format!("{path}\r\n"));
+    }
+    assert_eq!(client.pool().idle(), 0);
+}
+
+#[test]
+fn response_framing() {
+    let client = HttpClient::new();
+
+    // chunked with extensions and trailers
+    let address = scripted_server(
+        "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n\
+         5;ext=1\r\nhello\r\n7\r\n, world\r\n0\r\nexpires: never\r\n\r\n",
+        false,
+    );
+    let response = client.get(format!("http://{address}/")).unwrap();
+    assert_eq!(response.body_text().unwrap(), "hello, world");
+    assert_eq!(response.trailers()["expires"], ["never"]);
+    assert_eq!(client.pool().idle(), 1);
+
+    // delimited by close
+    let address = scripted_server("HTTP/1.1 200 OK\r\n\r\nline one\r\n\r\nline two", true);
+    let response = client.get(format!("http://{address}/")).unwrap();
+    assert_eq!(response.body_text().unwrap(), "line one\r\n\r\nline two");
+    assert_eq!(client.pool().idle(), 1);
+
+    // interim responses are skipped
+    let address = scripted_server(
+        "HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 103 Early Hints\r\nlink: </style.css>\r\n\r\n\
+         HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok",
+        false,
+    );
+    let response = client.get(format!("http://{address}/")).unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body_text().unwrap(), "ok");
+    assert!(response.header_first("link").is_none());
+
+    // no body for HEAD, 204 and 304
+    let address = scripted_server("HTTP/1.1 200 OK\r\ncontent-length: 10\r\n\r\n", false);
+    let response = client.head(format!("http://{address}/")).unwrap();
+    assert_eq!(response.header_first("content-length"), Some("10"));
+    assert!(response.body().is_empty());
+    for status in ["204 No Content", "304 Not Modified"] {
+        let address = scripted_server(
+            Box::leak(format!("HTTP/1.1 {status}\r\n\r\n").into_boxed_str()),
+            false,
+        );
+        let response = client.get(format!("http://{address}/")).unwrap();
+        assert!(response.body().is_empty());
+    }
+    assert_eq!(client.pool().idle(), 5);
+
+    // repeated content-length must be equal
+    let address = scripted_server(
+        "HTTP/1.1 200 OK\r\ncontent-length: 2, 2\r\ncontent-length: 2\r\n\r\nok",
+        false,
+    );
+    let response = client.get(format!("http://{address}/")).unwrap();
+    assert_eq!(response.body_text().unwrap(), "ok");
+    for content_length in ["2\r\ncontent-length: 3", "2, 3", "+2", ""] {
+        let address = scripted_server(
+            Box::leak(
+                format!("HTTP/1.1 200 OK\r\ncontent-length: {content_length}\r\n\r\nok")
+                    .into_boxed_str(),
+            ),
+            false,
+        );
+        assert!(client.get(format!("http://{address}/")).is_err());
+    }
+
+    // chunk size of hex digits only
+    let address = scripted_server(
+        "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n+2\r\nok\r\n0\r\n\r\n",
+        false,
+    );
+    assert!(client.get(format!("http://{address}/")).is_err());
+
+    // endless header, chunk size and trailer lines
+    let long = "a".repeat(100_000);
+    for response in [
+        format!("HTTP/1.1 200 OK\r\nx-long: {long}"),
+        format!("HTTP/1.1 200 OK\r\n{}", "x: 1\r\n".repeat(20_000)),
+        format!(
+            "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n1{}",
+            "0".repeat(10_000)
+        ),
+        format!("HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n0\r\nx: {long}"),
+    ] {
+        let address = scripted_server(Box::leak(response.into_boxed_str()), true);
+        assert!(client.get(format!("http://{address}/")).is_err());
+    }
+
+    // header line cut at size limit is not an empty line
+    let filler = "a".repeat(65_512);
+    let response = format!("HTTP/1.1 200 OK\r\nx: {filler}\r\nx-cut: 1\r\n\r\nok");
+    let address = scripted_server(Box::leak(response.into_boxed_str()), true);
+    let err = client.get(format!("http://{address}/")).unwrap_err();
+    assert_eq!(err.to_string(), "response header too large");
+
+    // connection closed within header
+    let address = scripted_server("HTTP/1.1 200 OK\r\ncontent-length: 2\r\n", true);
+    assert!(client.get(format!("http://{address}/")).is_err());
+}
+
+#[test]
+fn methods() {
+    // method of request and body
+    let address = serve(redirect_handler);
+    let client = HttpClient::new();
+    let url = format!("http://{address}/");
+    for (response, expected) in [
+        (client.get(&url), "Get  -\r\n"),
+        (client.post(&url, "a"), "Post a -\r\n"),
+        (client.put(&url, "b"), "Put b -\r\n"),
+        (client.patch(&url, "c"), "Patch c -\r\n"),
+        (client.delete(&url), "Delete  -\r\n"),
+        (client.options(&url), "Options  -\r\n"),
+    ] {
+        assert_eq!(response.unwrap().body_text().unwrap(), expected);
+    }
+}
+
+#[test]
+fn redirects() {
+    let address = serve(redirect_handler);
+    let client = HttpClient::new()
+        .with_header("Authorization", "Bearer secret")
+        .with_redirect_policy(RedirectPolicy::Limit(3));
+
+    // not followed by default
+    let response = HttpClient::new()
+        .get(format!("http://{address}/found"))
+        .unwrap();
+    assert_eq!(response.status(), 302);
+    assert_eq!(response.header_first("location"), Some("/target"));
+
+    // 303 and POST with 302 continue with GET, location is resolved
+    let response = client
+        .post(format!("http://{address}/a/see-other"), "data")
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.body_text().unwrap(), "Get  Bearer secret\r\n");
+    let response = client
+        .post(format!("http://{address}/found"), "data")
+        .unwrap();
+    assert_eq!(response.body_text().unwrap(), "Get  Bearer secret\r\n");
+    assert_eq!(response.redirects(), [format!("http://{address}/target")]);
+
+    // 307 preserves method and body
+    let response = client
+        .put(format!("http://{address}/temporary"), "data")
+        .unwrap();
+    assert_eq!(response.body_text().unwrap(), "Put data Bearer secret\r\n");
+
+    // authorization is not sent to other hosts
+    let response = client.get(format!("http://{address}/cross-host")).unwrap();
+    assert_eq!(response.body_text().unwrap(), "Get  -\r\n");
+
+    // maximum number of redirects
+    assert!(client.get(format!("http://{address}/loop")).is_err());
+}
+
+#[test]
+fn timeouts() {
+    let timeout_kind = |client: &HttpClient, timeouts: Option<Timeouts>| {
+        // server never responds
+        let address = scripted_server("", false);
+        let mut request = client.request_builder(HttpMethod::Get, format!("http://{address}/"));
+        if let Some(timeouts) = timeouts {
+            request = request.timeouts(timeouts);
+        }
+        let start = Instant::now();
+        let err = request.send().unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(2));
+        err.downcast_ref::<TimeoutError>().unwrap().kind
+    };
+
+    // defaults of client
+    let timeouts = Timeouts::new().read(Some(Duration::from_millis(100)));
+    let client = HttpClient::new().with_timeouts(timeouts);
+    assert_eq!(timeout_kind(&client, None), TimeoutKind::Read);
+
+    // overridden per request
+    let timeouts = Timeouts::new().total(Some(Duration::from_millis(100)));
+    assert_eq!(timeout_kind(&client, Some(timeouts)), TimeoutKind::Total);
+    let timeouts = timeouts.read(Some(Duration::from_secs(10)));
+    assert_eq!(timeout_kind(&client, Some(timeouts)), TimeoutKind::Total);
+
+    // other errors are no timeouts
+    let address = scripted_server("HTTP/1.1 200 OK\r\ncontent-length: x\r\n\r\n", false);
+    let err = client.get(format!("http://{address}/")).unwrap_err();
+    assert!(err.downcast_ref::<TimeoutError>().is_none());
+    assert_eq!(err.to_string(), "invalid content-length");
+}
+
+#[test]
+fn streaming_response() {
+    let client = HttpClient::new();
+
+    // headers first, then chunked body through Read
+    let address = scripted_server(
+        "HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\ntransfer-encoding: chunked\r\n\r\n\
+         6\r\nstream\r\n4\r\ning \r\n4\r\nbody\r\n0\r\nchecksum: 42\r\n\r\n",
+        false,
+    );
+    let mut response = client
+        .request_builder(HttpMethod::Get, format!("http://{address}/"))
+        .stream()
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header_first("content-type"), Some("text/plain"));
+    let mut start = [0u8; 4];
+    response.read_exact(&mut start).unwrap();
+    assert_eq!(&start, b"stre");
+    assert_eq!(client.pool().idle(), 0);
+
+    // rest copied to writer, connection reused afterwards
+    let mut rest = Vec::new();
+    assert_eq!(response.copy_to(&mut rest).unwrap(), 10);
+    assert_eq!(rest, b"aming body");
+    assert_eq!(response.trailers()["checksum"], ["42"]);
+    assert_eq!(client.pool().idle(), 1);
+
+    // length-delimited body
+    let address = scripted_server("HTTP/1.1 200 OK\r\ncontent-length: 5\r\n\r\nhello", false);
+    let mut response = client
+        .request_builder(HttpMethod::Get, format!("http://{address}/"))
+        .stream()
+        .unwrap();
+    let mut body = String::new();
+    response.read_to_string(&mut body).unwrap();
+    assert_eq!(body, "hello");
+    assert_eq!(client.pool().idle(), 2);
+
+    // body ended early
+    let address = scripted_server("HTTP/1.1 200 OK\r\ncontent-length: 10\r\n\r\nhello", true);
+    let mut response = client
+        .request_builder(HttpMethod::Get, format!("http://{address}/"))
+        .stream()
+        .unwrap();
+    assert!(response.copy_to(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn streaming_request() {
+    let client = HttpClient::new().with_redirect_policy(RedirectPolicy::Limit(3));
+    let data = "streamed ".repeat(4000);
+
+    // known length with content-length
+    let address = echo_server();
+    let response = client
+        .request_builder(HttpMethod::Put, format!("http://{address}/"))
+        .body_reader(data.as_bytes(), Some(data.len() as u64))
+        .send()
+        .unwrap();
+    let expected = format!("content-length: {}\n{data}", data.len());
+    assert_eq!(response.body_text().unwrap(), expected);
+
+    // unknown length chunked
+    let address = echo_server();
+    let response = client
+        .request_builder(HttpMethod::Post, format!("http://{address}/"))
+        .body_reader(data.as_bytes().chain(&b"end"[..]), None)
+        .send()
+        .unwrap();
+    let expected = format!("transfer-encoding: chunked\n{data}end");
+    assert_eq!(response.body_text().unwrap(), expected);
+
+    // reader shorter than length
+    let address = echo_server();
+    let result = client
+        .request_builder(HttpMethod::Put, format!("http://{address}/"))
+        .body_reader(&b"short"[..], Some(10))
+        .send();
+    assert!(result.is_err());
+
+    // streamed body cannot be repeated for 307
+    let address = serve(redirect_handler);
+    let result = client
+        .request_builder(HttpMethod::Put, format!("http://{address}/temporary"))
+        .body_reader(&b"data"[..], Some(4))
+        .send();
+    assert!(result.is_err());
+}
+
+#[test]
+fn multipart_form() {
+    let client = HttpClient::new();
+    let path = std::env::temp_dir().join("kern-multipart-test.txt");
+    std::fs::write(&path, "file content").unwrap();
+
+    // known length with content-length
+    let form = MultipartForm::new()
+        .text("title", "hello")
+        .bytes("data", "a\"b.bin", "application/x-test", &b"\x00\x01"[..])
+        .file("upload", &path)
+        .unwrap();
+    let boundary = form.boundary().to_string();
+    assert!(boundary.len() > 30);
+    assert_ne!(boundary, MultipartForm::new().boundary());
+    let length = form.length().unwrap();
+    let address = echo_server();
+    let response = client
+        .request_builder(HttpMethod::Post, format!("http://{address}/"))
+        .multipart(form)
+        .send()
+        .unwrap();
+    let expected = format!(
+        "content-length: {length}\n\
+         Content-Type: multipart/form-data; boundary={boundary}\n\
+         --{boundary}\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nhello\r\n\
+         --{boundary}\r\nContent-Disposition: form-data; name=\"data\"; filename=\"a%22b.bin\"\r\n\
+         Content-Type: application/x-test\r\n\r\n\x00\x01\r\n\
+         --{boundary}\r\nContent-Disposition: form-data; name=\"upload\"; filename=\"kern-multipart-test.txt\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\nfile content\r\n\
+         --{boundary}--\r\n"
+    );
+    assert_eq!(response.body_text().unwrap(), expected);
+    std::fs::remove_file(&path).unwrap();
+
+    // unknown length chunked, line breaks removed from content type
+    let content_type = "text/plain\r\nX-Injected: 1";
+    let form = MultipartForm::new().reader("log", "log.txt", content_type, &b"line"[..], None);
+    let boundary = form.boundary().to_string();
+    assert_eq!(form.length(), None);
+    let address = echo_server();
+    let response = client
+        .request_builder(HttpMethod::Post, format!("http://{address}/"))
+        .multipart(form)
+        .send()
+        .unwrap();
+    let expected = format!(
+        "transfer-encoding: chunked\n\
+         Content-Type: multipart/form-data; boundary={boundary}\n\
+         --{boundary}\r\nContent-Disposition: form-data; name=\"log\"; filename=\"log.txt\"\r\n\
+         Content-Type: text/plainX-Injected: 1\r\n\r\nline\r\n\
+         --{boundary}--\r\n"
+    );
+    assert_eq!(response.body_text().unwrap(), expected);
+}
+
+#[test]
+fn cookie_jar() {
+    // domain, path and secure rules
+    let jar = CookieJar::new();
+    let url = "http://www.example.com/docs/page";
+    jar.store(url, "a=1").unwrap();
+    jar.store(url, "b=2; Domain=.Example.com; Path=/").unwrap();
+    jar.store(url, "c=3; Path=/docs/intro").unwrap();
+    jar.store(url, "d=4; Secure").unwrap();
+    jar.store(url, "e=5; Domain=other.com").unwrap();
+    jar.store(url, "f=6; Domain=com").unwrap();
+    jar.store(url, "g=7; Max-Age=0").unwrap();
+    jar.store(
+        "https://www.example.com/",
+        "h=8; Secure; Expires=Wed, 21 Oct 2099 07:28:00 GMT",
+    )
+    .unwrap();
+    jar.store(url, "invalid").unwrap();
+    assert_eq!(jar.header(url).unwrap().unwrap(), "a=1; b=2");
+    assert_eq!(
+        jar.header("http://www.example.com/docs/intro/more")
+            .unwrap()
+            .unwrap(),
+        "c=3; a=1; b=2"
+    );
+    assert_eq!(jar.header("http://example.com/").unwrap().unwrap(), "b=2");
+    assert_eq!(
+        jar.header("https://www.example.com/").unwrap().unwrap(),
+        "b=2; h=8"
+    );
+    assert_eq!(
+        jar.header("http://www.example.com/documents")
+            .unwrap()
+            .unwrap(),
+        "b=2"
+    );
+    assert!(jar.header("http://other.com/").unwrap().is_none());
+
+    // replace and delete
+    jar.store(url, "a=changed").unwrap();
+    jar.store(
+        url,
+        "b=; Domain=example.com; Path=/; Expires=Thu, 01 Jan 1970 00:00:00 GMT",
+    )
+    .unwrap();
+    assert_eq!(jar.header(url).unwrap().unwrap(), "a=changed");
+
+    // only persistent cookies are saved
+    let path = std::env::temp_dir().join("kern-cookie-test.txt");
+    jar.store(
+        url,
+        "p=1; Domain=example.com; Path=/; Max-Age=3600; HttpOnly",
+    )
+    .unwrap();
+    jar.save(&path).unwrap();
+    let loaded = CookieJar::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let mut names: Vec<String> = loaded
+        .cookies()
+        .into_iter()
+        .map(|cookie| cookie.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, ["h", "p"]);
+    let cookie = loaded
+        .cookies()
+        .into_iter()
+        .find(|cookie| cookie.name == "p")
+        .unwrap();
+    assert!(cookie.http_only && !cookie.host_only && !cookie.secure);
+    assert_eq!(
+        loaded.header("http://sub.example.com/").unwrap().unwrap(),
+        "p=1"
+    );
+
+    // client stores cookies of redirects and sends them
+    let address = serve(cookie_handler);
+    let jar = Arc::new(CookieJar::new());
+    let client = HttpClient::new()
+        .with_redirect_policy(RedirectPolicy::Limit(3))
+        .with_cookie_jar(jar.clone());
+    let response = client.get(format!("http://{address}/login")).unwrap();
+    assert_eq!(response.body_text().unwrap(), "session=abc\r\n");
+    assert_eq!(jar.cookies().len(), 1);
+
+    // explicit header replaces jar
+    let response = client
+        .request_builder(HttpMethod::Get, format!("http://{address}/"))
+        .header("Cookie", "own=1")
+        .send()
+        .unwrap();
+    assert_eq!(response.body_text().unwrap(), "own=1\r\n");
+
+    // multiple Set-Cookie headers in one response
+    let address = scripted_server(
+        "HTTP/1.1 200 OK\r\nset-cookie: x=1\r\nSet-Cookie: y=2\r\ncontent-length: 0\r\n\r\n",
+        false,
+    );
+    let jar = Arc::new(CookieJar::new());
+    let client = HttpClient::new().with_cookie_jar(jar.clone());
+    client.get(format!("http://{address}/")).unwrap();
+    assert_eq!(
+        jar.header(&format!("http://{address}/")).unwrap().unwrap(),
+        "x=1; y=2"
+    );
+}
+
+#[test]
+fn proxy() {
+    // absolute-form with basic auth of proxy URL
+    let address = proxy_server("200 OK");
+    let proxy = Proxy::new(&format!("http://user:p%40ss@{address}")).unwrap();
+    assert_eq!(proxy.addr(), address.to_string());
+    let client = HttpClient::new().with_proxy(ProxyConfig::new().http(proxy));
+    let response = client.get("http://example.invalid:8080/path").unwrap();
+    assert_eq!(
+        response.body_text().unwrap(),
+        "GET http://example.invalid:8080/path HTTP/1.1 Basic dXNlcjpwQHNz"
+    );
+
+    // https is tunnelled with CONNECT, refused tunnel fails
+    let address = proxy_server("407 Proxy Authentication Required");
+    let proxy = Proxy::new(&address.to_string())
+        .unwrap()
+        .basic_auth("user", "secret");
+    let client = HttpClient::new().with_proxy(ProxyConfig::new().all(proxy));
+    let err = client.get("https://example.invalid/").unwrap_err();
+    assert_eq!(err.to_string(), "proxy refused tunnel with status 407");
+
+    // hosts of no_proxy are reached directly
+    let address = serve(redirect_handler);
+    let proxy = Proxy::new("http://127.0.0.1:9").unwrap();
+    let config = ProxyConfig::new()
+        .all(proxy)
+        .no_proxy("example.com, 127.0.0.1");
+    let client = HttpClient::new().with_proxy(config);
+    let response = client.get(format!("http://{address}/")).unwrap();
+    assert_eq!(response.body_text().unwrap(), "Get  -\r\n");
+
+    // unsupported proxies
+    assert!(Proxy::new("socks5://127.0.0.1:1080").is_err());
+    assert!(Proxy::new("http://").is_err());
+}
@@ -0,0 +1,68 @@
+use kern::http::date::{format_http_date, parse_cookie_date, parse_http_date};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn http_date() {
+    // RFC 9110 example in all three formats
+    let time = UNIX_EPOCH + Duration::from_secs(784111777);
+    assert_eq!(format_http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+    assert_eq!(
+        parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap(),
+        time
+    );
+    assert_eq!(
+        parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT").unwrap(),
+        time
+    );
+    assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994").unwrap(), time);
+
+    // epoch, leap day and before epoch
+    assert_eq!(
+        format_http_date(UNIX_EPOCH),
+        "Thu, 01 Jan 1970 00:00:00 GMT"
+    );
+    let leap_day = parse_http_date("Thu, 29 Feb 2024 23:59:59 GMT").unwrap();
+    assert_eq!(format_http_date(leap_day), "Thu, 29 Feb 2024 23:59:59 GMT");
+    let before = parse_http_date("Wed, 31 Dec 1969 23:59:59 GMT").unwrap();
+    assert_eq!(before, UNIX_EPOCH - Duration::from_secs(1));
+    assert_eq!(format_http_date(before), "Wed, 31 Dec 1969 23:59:59 GMT");
+
+    // invalid dates
+    assert!(parse_http_date("").is_err());
+    assert!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT").is_err());
+    assert!(parse_http_date("Sun, 06 Nov 1994 24:49:37 GMT").is_err());
+    assert!(parse_http_date("Sun, 06 Nov 1994 08:49:37 UTC").is_err());
+
+    // out of range year, day of month and signed time
+    assert!(parse_http_date("Sun, 06 Nov 300000000000 08:49:37 GMT").is_err());
+    assert!(parse_http_date("Sun Nov  6 08:49:37 300000000000").is_err());
+    assert!(parse_http_date("Sun, 06 Nov 94 08:49:37 GMT").is_err());
+    assert!(parse_http_date("Sunday, 06-Nov-1994 08:49:37 GMT").is_err());
+    assert!(parse_http_date("Fri, 31 Feb 2023 08:49:37 GMT").is_err());
+    assert!(parse_http_date("Thu, 29 Feb 2023 08:49:37 GMT").is_err());
+    assert!(parse_http_date("Sun, 06 Nov 1994 08:-1:37 GMT").is_err());
+    assert!(parse_http_date("Sun, 06 Nov 1994 08:+9:37 GMT").is_err());
+    assert!(parse_http_date("Sun, +6 Nov 1994 08:49:37 GMT").is_err());
+}
+
+#[test]
+fn cookie_date() {
+    // HTTP-date and common variants
+    let time = UNIX_EPOCH + Duration::from_secs(784111777);
+    for date in [
+        "Sun, 06 Nov 1994 08:49:37 GMT",
+        "Sunday, 06-Nov-94 08:49:37 GMT",
+        "Sun Nov  6 08:49:37 1994",
+        "Sun, 06-Nov-1994 08:49:37 GMT",
+        "6 november 1994 8:49:37",
+    ] {
+        assert_eq!(parse_cookie_date(date).unwrap(), time, "{date}");
+    }
+
+    // missing fields and out of range
+    assert!(parse_cookie_date("").is_err());
+    assert!(parse_cookie_date("Sun, 06 Nov 1994").is_err());
+    assert!(parse_cookie_date("Sun, 06 Nov 1600 08:49:37 GMT").is_err());
+    assert!(parse_cookie_date("Fri, 31 Feb 2023 08:49:37 GMT").is_err());
+    assert!(parse_cookie_date("Sun, 06 Nov 1994 08:60:37 GMT").is_err());
+}
@@ -0,0 +1,1084 @@
+use kern::byte::{base64_encode, constant_time_eq};
+use kern::http::client::HttpClient;
+use kern::http::current_request_id;
+use kern::http::date::parse_http_date;
+use kern::http::server::{
+    AuthPolicy, CorsPolicy, Handler, HttpError, HttpMethod, HttpRequest, HttpServer,
+    HttpServerBuilder, HttpSettings, Listener, Negotiation, QualityItem, RequestSummary,
+    ResponseData, ServerListener, TestRequest, TestResponse, TestServer, conditional_response,
+    parse_quality_list, redirect, respond,
+};
+use kern::{Fail, Result};
+use std::io::prelude::*;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+static UPSTREAM: OnceLock<std::net::SocketAddr> = OnceLock::new();
+static STARTED: AtomicUsize = AtomicUsize::new(0);
+static FINISHED: Mutex<Vec<RequestSummary>> = Mutex::new(Vec::new());
+
+fn handler(req: HttpRequest) -> Result<Vec<u8>> {
+    Ok(respond(req.ip(), "text/plain", None))
+}
+
+fn body_handler(req: HttpRequest) -> Result<Vec<u8>> {
+    Ok(respond(req.body(), "text/plain", None))
+}
+
+fn vary_handler(_req: HttpRequest) -> Result<Vec<u8>> {
+    let data = ResponseData::new().header("Vary", "Accept-Encoding");
+    Ok(respond("content", "text/plain", data.build()))
+}
+
+fn status_handler(req: HttpRequest) -> Result<Vec<u8>> {
+    Ok(match req.url() {
+        "/raw" => b"HTTP/1.1 204 No Content\r\ncontent-length: 7\r\n\r\ncontent\r\n".to_vec(),
+        "/not-modified" => respond(
+            "content",
+            "text/plain",
+            ResponseData::not_modified().build(),
+        ),
+        "/error" => return Fail::from("handler failed"),
+        "/teapot" => return Err(HttpError::boxed("418 I'm a teapot", "short and stout")),
+        _ => respond(
+            format!("{:?} {}", req.method(), req.head()),
+            "text/plain",
+            None,
+        ),
+    })
+}
+
+fn request_id_handler(req: HttpRequest) -> Result<Vec<u8>> {
+    // forward to upstream or echo received id
+    Ok(match UPSTREAM.get() {
+        Some(upstream) if req.url() == "/forward" => {
+            let response = HttpClient::new().get(format!("http://{upstream}/"))?;
+            respond(response.body(), "text/plain", None)
+        }
+        _ => respond(
+            format!("{} {:?}", req.id(), current_request_id()),
+            "text/plain",
+            None,
+        ),
+    })
+}
+
+fn negotiate_handler(req: HttpRequest) -> Result<Vec<u8>> {
+    Ok(match req.negotiate(&["text/html", "application/json"]) {
+        Ok(content_type) => respond(content_type, content_type, None),
+        Err(response) => response,
+    })
+}
+
+fn conditional_handler(req: HttpRequest) -> Result<Vec<u8>> {
+    let last_modified = parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT")?;
+    let response = match req.url() {
+        "/missing" => conditional_response(&req, false, None, None),
+        "/unversioned" => conditional_response(&req, true, None, None),
+        _ => conditional_response(&req, true, Some("\"v1\""), Some(last_modified)),
+    };
+    if let Some(response) = response {
+        return Ok(response);
+    }
+    Ok(respond("content", "text/plain", None))
+}
+
+fn serve(settings: HttpSettings, handler: Handler) -> std::net::SocketAddr {
+    // listen on random port
+    let listener = Listener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    HttpServer::with_listener(
+        listener,
+        Arc::new(settings),
+        handler,
+        |context| respond(context.error.to_string(), "text/plain", None),
+        #[cfg(feature = "tls")]
+        None,
+    )
+    .unwrap();
+    address
+}
+
+fn request_tcp(address: std::net::SocketAddr, request: &str) -> String {
+    // send request and read response
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+fn h2_frame(kind: u8, flags: u8, id: u32, payload: &[u8]) -> Vec<u8> {
+    // length, type, flags, stream identifier, payload
+    let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+    frame.extend_from_slice(&[kind, flags]);
+    frame.extend_from_slice(&id.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+fn h2_read_frame(stream: &mut TcpStream) -> (u8, u8, u32, Vec<u8>) {
+    // read header and payload
+    let mut header = [0u8; 9];
+    stream.read_exact(&mut header).unwrap();
+    let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).unwrap();
+    let id = u32::from_be_bytes([header[5], header[6], header[7], header[8]]);
+    (header[3], header[4], id, payload)
+}
+
+fn h2_headers(headers: &[(&str, &str)]) -> Vec<u8> {
+    // HPACK literals without indexing and huffman encoding
+    let mut block = Vec::new();
+    for (name, value) in headers {
+        block.push(0);
+        block.push(name.len() as u8);
+        block.extend_from_slice(name.as_bytes());
+        block.push(value.len() as u8);
+        block.extend_from_slice(value.as_bytes());
+    }
+    block
+}
+
+fn h2_connect(address: std::net::SocketAddr, settings: &[u8]) -> TcpStream {
+    // send preface and settings, skip server settings and acknowledgement
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream
+        .set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .unwrap();
+    stream
+        .write_all(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n")
+        .unwrap();
+    stream.write_all(&h2_frame(4, 0, 0, settings)).unwrap();
+    let (kind, flags, _, _) = h2_read_frame(&mut stream);
+    assert_eq!((kind, flags), (4, 0));
+    let (kind, flags, _, _) = h2_read_frame(&mut stream);
+    assert_eq!((kind, flags), (4, 1));
+    stream
+}
+
+#[cfg(unix)]
+fn request_unix(path: &str, request: &str) -> String {
+    // send request and read response
+    let mut stream = std::os::unix::net::UnixStream::connect(path).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_socket() {
+    use std::fs::{metadata, write};
+    use std::os::unix::fs::PermissionsExt;
+
+    // a regular file is never removed
+    let path = std::env::temp_dir().join(format!("kern-test-{}.sock", std::process::id()));
+    write(&path, "no socket").unwrap();
+    assert!(Listener::bind_unix(&path, None).is_err());
+    std::fs::remove_file(&path).unwrap();
+
+    // stale socket is removed
+    drop(Listener::bind_unix(&path, None).unwrap());
+    HttpServerBuilder::new()
+        .unix(&path, Some(0o600))
+        .handler(handler)
+        .build()
+        .unwrap();
+    assert_eq!(metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+    // socket in use
+    assert!(Listener::bind_unix(&path, None).is_err());
+
+    // ip of unix socket peer
+    let path = path.to_str().unwrap();
+    let response = request_unix(path, "GET / HTTP/1.1\r\n\r\n");
+    assert!(response.ends_with("\r\n\r\nunix\r\n"));
+    let response = request_unix(path, "GET / HTTP/1.1\r\nX-Real-IP: 10.0.0.1\r\n\r\n");
+    assert!(response.ends_with("\r\n\r\n10.0.0.1\r\n"));
+}
+
+#[test]
+fn multiple_listeners() {
+    // no systemd sockets passed
+    #[cfg(unix)]
+    assert!(Listener::systemd().unwrap().is_empty());
+
+    // two listeners sharing one handler
+    let listeners = vec![
+        ServerListener::new(Listener::bind("127.0.0.1:0").unwrap()),
+        Listener::bind("127.0.0.1:0").unwrap().into(),
+    ];
+    let server = HttpServer::with_listeners(
+        listeners,
+        Arc::new(HttpSettings::default()),
+        handler,
+        |context| respond(context.error.to_string(), "text/plain", None),
+    )
+    .unwrap();
+    assert_eq!(server.listeners().len(), 2);
+    for listener in server.listeners() {
+        let address = listener.listener().local_addr().unwrap();
+        let response = request_tcp(address, "GET / HTTP/1.1\r\n\r\n");
+        assert!(response.ends_with("\r\n\r\n127.0.0.1\r\n"));
+
+        // connections are not kept alive
+        assert!(response.contains("\r\nconnection: close\r\n"));
+    }
+
+    // at least one listener required
+    assert!(
+        HttpServer::with_listeners(
+            Vec::new(),
+            Arc::new(HttpSettings::default()),
+            handler,
+            |context| respond(context.error.to_string(), "text/plain", None),
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn http2() {
+    // disabled by default
+    let address = serve(HttpSettings::new(), body_handler);
+    let response = request_tcp(address, "PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 "));
+
+    // request with body across multiple DATA frames
+    let address = serve(HttpSettings::new().http2(true), body_handler);
+    let mut stream = h2_connect(address, &[]);
+    let headers = h2_headers(&[
+        (":method", "POST"),
+        (":scheme", "http"),
+        (":authority", "localhost"),
+        (":path", "/"),
+    ]);
+    stream.write_all(&h2_frame(1, 0x4, 1, &headers)).unwrap();
+    let body: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+    let mut chunks = body.chunks(16_384).peekable();
+    while let Some(chunk) = chunks.next() {
+        let flags = if chunks.peek().is_none() { 0x1 } else { 0 };
+        stream.write_all(&h2_frame(0, flags, 1, chunk)).unwrap();
+    }
+
+    // response stops at initial flow control window
+    let mut received = Vec::new();
+    let mut status = None;
+    while received.len() < 65_535 {
+        let (kind, _, id, payload) = h2_read_frame(&mut stream);
+        match kind {
+            // HEADERS with :status 200 from static table
+            1 => status = Some((id, payload[0])),
+            0 => {
+                assert_eq!(id, 1);
+                assert!(payload.len() <= 16_384);
+                received.extend_from_slice(&payload);
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(status, Some((1, 0x88)));
+    assert_eq!(received.len(), 65_535);
+
+    // rest sent after window updates
+    let increment = 100_000u32.to_be_bytes();
+    stream.write_all(&h2_frame(8, 0, 0, &increment)).unwrap();
+    stream.write_all(&h2_frame(8, 0, 1, &increment)).unwrap();
+    loop {
+        let (kind, flags, id, payload) = h2_read_frame(&mut stream);
+        if kind == 0 {
+            assert_eq!(id, 1);
+            received.extend_from_slice(&payload);
+            if flags & 0x1 != 0 {
+                break;
+            }
+        }
+    }
+    assert_eq!(received.len(), body.len() + 2);
+    assert!(received.starts_with(&body) && received.ends_with(b"\r\n"));
+
+    // stream window overflow resets stream
+    let mut stream = h2_connect(address, &[]);
+    stream.write_all(&h2_frame(1, 0x4, 1, &headers)).unwrap();
+    let increment = 0x7fff_ffffu32.to_be_bytes();
+    stream.write_all(&h2_frame(8, 0, 1, &increment)).unwrap();
+    let frame = loop {
+        let frame = h2_read_frame(&mut stream);
+        if frame.0 == 3 {
+            break frame;
+        }
+    };
+    assert_eq!(frame, (3, 0, 1, 3u32.to_be_bytes().to_vec()));
+
+    // initial window size above 2^31-1 closes connection
+    let mut stream = h2_connect(address, &[]);
+    let settings = [0, 4, 0x80, 0, 0, 0];
+    stream.write_all(&h2_frame(4, 0, 0, &settings)).unwrap();
+    let (kind, _, _, payload) = h2_read_frame(&mut stream);
+    assert_eq!((kind, &payload[4..]), (7, &3u32.to_be_bytes()[..]));
+
+    // initial window size overflowing open stream closes connection
+    let mut stream = h2_connect(address, &[]);
+    stream.write_all(&h2_frame(1, 0x4, 1, &headers)).unwrap();
+    stream
+        .write_all(&h2_frame(8, 0, 1, &1000u32.to_be_bytes()))
+        .unwrap();
+    let settings = [0, 4, 0x7f, 0xff, 0xff, 0xff];
+    stream.write_all(&h2_frame(4, 0, 0, &settings)).unwrap();
+    let frame = loop {
+        let frame = h2_read_frame(&mut stream);
+        if frame.0 == 7 {
+            break frame;
+        }
+    };
+    assert_eq!(&frame.3[4..], &3u32.to_be_bytes()[..]);
+
+    // header list expanding from dynamic table references is rejected
+    let mut stream = h2_connect(address, &[]);
+    let mut bomb = headers.clone();
+    bomb.extend_from_slice(&[0x40, 1, b'x', 0x7f, 0xa1, 0x1e]);
+    bomb.extend_from_slice(&[b'a'; 4000]);
+    bomb.extend_from_slice(&[0xbe; 1000]);
+    stream.write_all(&h2_frame(1, 0x5, 1, &bomb)).unwrap();
+    let body = loop {
+        let (kind, _, id, payload) = h2_read_frame(&mut stream);
+        if kind == 0 && id == 1 {
+            break payload;
+        }
+    };
+    assert_eq!(body, b"Max header size exceeded\r\n");
+
+    // dynamic table stays in sync for the next stream
+    let mut headers = headers.clone();
+    headers.push(0xbe);
+    stream.write_all(&h2_frame(1, 0x5, 3, &headers)).unwrap();
+    let status = loop {
+        let (kind, _, id, payload) = h2_read_frame(&mut stream);
+        if kind == 1 && id == 3 {
+            break payload[0];
+        }
+    };
+    assert_eq!(status, 0x88);
+
+    // malformed fields reset the stream instead of reaching the handler
+    let mut stream = h2_connect(address, &[]);
+    let malformed = [
+        (":method", "GET"),
+        (":scheme", "http"),
+        (":authority", "localhost"),
+        (":path", "/"),
+    ];
+    let cases: [&[(&str, &str)]; 6] = [
+        &[("x-a", "b\r\nauthorization: Basic")],
+        &[("X-A", "b")],
+        &[("x:a", "b")],
+        &[("x-a", "b\0")],
+        &[(":method", "GET / HTTP/1.1\r\nx-a:")],
+        &[(":path", "/ HTTP/1.1")],
+    ];
+    for (i, case) in cases.iter().enumerate() {
+        let mut fields = malformed.to_vec();
+        for &(name, value) in *case {
+            match fields.iter_mut().find(|(n, _)| *n == name) {
+                Some(field) => field.1 = value,
+                None => fields.push((name, value)),
+            }
+        }
+        let id = i as u32 * 2 + 1;
+        stream
+            .write_all(&h2_frame(1, 0x5, id, &h2_headers(&fields)))
+            .unwrap();
+        let frame = h2_read_frame(&mut stream);
+        assert_eq!(frame, (3, 0, id, 1u32.to_be_bytes().to_vec()));
+    }
+}
+
+#[test]
+fn cors() {
+    let cors = CorsPolicy::new()
+        .origin("https://app.example.com")
+        .origin("https://*.example.org")
+        .methods(&[HttpMethod::Get, HttpMethod::Put])
+        .allow_header("Content-Type")
+        .expose_header("X-Total")
+        .credentials(true)
+        .max_age(Some(600));
+    assert!(cors.allows_origin("https://a.example.org"));
+    assert!(!cors.allows_origin("https://example.org"));
+    assert!(!cors.allows_origin("https://app.example.com.evil"));
+    let address = serve(HttpSettings::new().cors(Some(cors)), handler);
+
+    // preflight answered without handler
+    let response = request_tcp(
+        address,
+        "OPTIONS /api HTTP/1.1\r\nOrigin: https://app.example.com\r\nAccess-Control-Request-Method: PUT\r\nAccess-Control-Request-Headers: content-type\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 204 No Content\r\n"));
+    assert!(response.contains("access-control-allow-origin: https://app.example.com\r\n"));
+    assert!(response.contains("access-control-allow-methods: GET, PUT\r\n"));
+    assert!(response.contains("access-control-allow-headers: content-type\r\n"));
+    assert!(response.contains("access-control-allow-credentials: true\r\n"));
+    assert!(response.contains("access-control-max-age: 600\r\n"));
+    assert!(!response.contains("127.0.0.1"));
+
+    // preflight with disallowed method, header or origin
+    for request in [
+        "OPTIONS / HTTP/1.1\r\nOrigin: https://app.example.com\r\nAccess-Control-Request-Method: DELETE\r\n\r\n",
+        "OPTIONS / HTTP/1.1\r\nOrigin: https://app.example.com\r\nAccess-Control-Request-Method: GET\r\nAccess-Control-Request-Headers: x-secret\r\n\r\n",
+        "OPTIONS / HTTP/1.1\r\nOrigin: https://evil.com\r\nAccess-Control-Request-Method: GET\r\n\r\n",
+    ] {
+        let response = request_tcp(address, request);
+        assert!(response.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+        assert!(!response.contains("access-control-allow-origin"));
+    }
+
+    // normal responses are decorated for allowed origins only
+    let response = request_tcp(
+        address,
+        "GET / HTTP/1.1\r\nOrigin: https://b.example.org\r\n\r\n",
+    );
+    assert!(response.contains("access-control-allow-origin: https://b.example.org\r\n"));
+    assert!(response.contains("access-control-expose-headers: X-Total\r\n"));
+    assert!(response.contains("vary: Origin\r\n"));
+    assert!(response.ends_with("\r\n\r\n127.0.0.1\r\n"));
+    let response = request_tcp(
+        address,
+        "GET / HTTP/1.1\r\nOrigin: https://evil.com\r\n\r\n",
+    );
+    assert!(!response.contains("access-control-allow-origin"));
+
+    // Origin merged into Vary header of handler
+    let cors = CorsPolicy::new().origin("https://app.example.com");
+    let address = serve(HttpSettings::new().cors(Some(cors)), vary_handler);
+    let response = request_tcp(
+        address,
+        "GET / HTTP/1.1\r\nOrigin: https://app.example.com\r\n\r\n",
+    );
+    assert!(response.contains("\r\nVary: Accept-Encoding, Origin\r\n"));
+    assert_eq!(response.matches("ary: ").count(), 1);
+    assert!(response.ends_with("\r\n\r\ncontent\r\n"));
+
+    // any origin without credentials
+    let address = serve(
+        HttpSettings::new().cors(Some(CorsPolicy::new().any_origin())),
+        handler,
+    );
+    let response = request_tcp(
+        address,
+        "GET / HTTP/1.1\r\nOrigin: https://evil.com\r\n\r\n",
+    );
+    assert!(response.contains("access-control-allow-origin: *\r\n"));
+}
+
+#[test]
+fn auth() {
+    let auth = AuthPolicy::new("kern \"test\"")
+        .basic("/admin", |user, password| {
+            constant_time_eq(user, "admin") & constant_time_eq(password, "pass:word")
+        })
+        .bearer("/api/", |token| constant_time_eq(token, "token123"));
+    assert!(auth.authenticator("/adminx").is_none());
+    assert!(auth.authenticator("/admin/users").is_some());
+    for bypass in [
+        "/public/../admin",
+        "//admin",
+        "/%61dmin",
+        "/./admin/",
+        "/x/%2e%2e/admin",
+    ] {
+        assert!(auth.authenticator(bypass).is_some(), "{bypass}");
+    }
+    let address = serve(HttpSettings::new().auth(Some(auth)), handler);
+
+    // unprotected path
+    let response = request_tcp(address, "GET /public HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+
+    // basic challenge and credentials
+    let response = request_tcp(address, "GET /admin HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+    for bypass in ["/public/../admin", "//admin", "/%61dmin", "/api/../api//x"] {
+        let response = request_tcp(address, &format!("GET {bypass} HTTP/1.1\r\n\r\n"));
+        assert!(
+            response.starts_with("HTTP/1.1 401 Unauthorized\r\n"),
+            "{bypass}"
+        );
+    }
+    assert!(
+        response
+            .contains("www-authenticate: Basic realm=\"kern \\\"test\\\"\", charset=\"UTF-8\"\r\n")
+    );
+    let credentials = base64_encode("admin:pass:word");
+    let response = request_tcp(
+        address,
+        &format!("GET /admin/x HTTP/1.1\r\nAuthorization: basic {credentials}\r\n\r\n"),
+    );
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    let credentials = base64_encode("admin:wrong");
+    let response = request_tcp(
+        address,
+        &format!("GET /admin HTTP/1.1\r\nAuthorization: Basic {credentials}\r\n\r\n"),
+    );
+    assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+
+    // bearer challenge and token
+    let response = request_tcp(address, "GET /api/items HTTP/1.1\r\n\r\n");
+    assert!(response.contains("www-authenticate: Bearer realm=\"kern \\\"test\\\"\"\r\n"));
+    let response = request_tcp(
+        address,
+        "GET /api/items HTTP/1.1\r\nAuthorization: Bearer wrong\r\n\r\n",
+    );
+    assert!(response.contains("error=\"invalid_token\"\r\n"));
+    let response = request_tcp(
+        address,
+        "GET /api/items HTTP/1.1\r\nAuthorization: Bearer token123\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+}
+
+#[test]
+fn conditional() {
+    let address = serve(HttpSettings::new(), conditional_handler);
+    let status = |request: &str| {
+        let response = request_tcp(address, &format!("{request}\r\n\r\n"));
+        assert!(response.contains("\r\ndate: "));
+        response.lines().next().unwrap().to_string()
+    };
+
+    // no preconditions
+    assert_eq!(status("GET / HTTP/1.1"), "HTTP/1.1 200 OK");
+
+    // If-None-Match (weak comparison)
+    assert_eq!(
+        status("GET / HTTP/1.1\r\nIf-None-Match: \"a,b\", W/\"v1\""),
+        "HTTP/1.1 304 Not Modified"
+    );
+    assert_eq!(
+        status("GET / HTTP/1.1\r\nIf-None-Match: \"v2\""),
+        "HTTP/1.1 200 OK"
+    );
+    assert_eq!(
+        status("PUT / HTTP/1.1\r\nIf-None-Match: *"),
+        "HTTP/1.1 412 Precondition Failed"
+    );
+
+    // If-Match (strong comparison)
+    assert_eq!(
+        status("PUT / HTTP/1.1\r\nIf-Match: \"v1\""),
+        "HTTP/1.1 200 OK"
+    );
+    assert_eq!(
+        status("PUT / HTTP/1.1\r\nIf-Match: W/\"v1\""),
+        "HTTP/1.1 412 Precondition Failed"
+    );
+
+    // "*" depends on existence, not on etag
+    assert_eq!(
+        status("PUT /unversioned HTTP/1.1\r\nIf-Match: *"),
+        "HTTP/1.1 200 OK"
+    );
+    assert_eq!(
+        status("PUT /unversioned HTTP/1.1\r\nIf-None-Match: *"),
+        "HTTP/1.1 412 Precondition Failed"
+    );
+    assert_eq!(
+        status("GET /unversioned HTTP/1.1\r\nIf-None-Match: *"),
+        "HTTP/1.1 304 Not Modified"
+    );
+    assert_eq!(
+        status("PUT /missing HTTP/1.1\r\nIf-Match: *"),
+        "HTTP/1.1 412 Precondition Failed"
+    );
+    assert_eq!(
+        status("PUT /missing HTTP/1.1\r\nIf-None-Match: *"),
+        "HTTP/1.1 200 OK"
+    );
+    assert_eq!(
+        status("PUT /missing HTTP/1.1\r\nIf-Unmodified-Since: Sat, 05 Nov 1994 08:49:37 GMT"),
+        "HTTP/1.1 200 OK"
+    );
+
+    // If-Modified-Since and If-Unmodified-Since
+    assert_eq!(
+        status("GET / HTTP/1.1\r\nIf-Modified-Since: Sun, 06 Nov 1994 08:49:37 GMT"),
+        "HTTP/1.1 304 Not Modified"
+    );
+    assert_eq!(
+        status("GET / HTTP/1.1\r\nIf-Modified-Since: Sat, 05 Nov 1994 08:49:37 GMT"),
+        "HTTP/1.1 200 OK"
+    );
+    assert_eq!(
+        status("PUT / HTTP/1.1\r\nIf-Unmodified-Since: Sat, 05 Nov 1994 08:49:37 GMT"),
+        "HTTP/1.1 412 Precondition Failed"
+    );
+
+    assert_eq!(
+        status("GET / HTTP/1.1\r\nIf-Modified-Since: Sun, 06 Nov 300000000000 08:49:37 GMT"),
+        "HTTP/1.1 200 OK"
+    );
+
+    // If-None-Match takes precedence over If-Modified-Since
+    assert_eq!(
+        status(
+            "GET / HTTP/1.1\r\nIf-None-Match: \"v2\"\r\nIf-Modified-Since: Sun, 06 Nov 1994 08:49:37 GMT"
+        ),
+        "HTTP/1.1 200 OK"
+    );
+}
+
+#[test]
+fn negotiation() {
+    // parse and sort by quality
+    assert_eq!(
+        parse_quality_list("text/html;level=1;q=0.5, application/json , */*;q=0.1, bad;q=x"),
+        vec![
+            QualityItem {
+                value: "application/json",
+                quality: 1.0
+            },
+            QualityItem {
+                value: "text/html",
+                quality: 0.5
+            },
+            QualityItem {
+                value: "*/*",
+                quality: 0.1
+            },
+        ]
+    );
+
+    // media types with wildcards and specificity
+    let media = Negotiation::MediaType;
+    let offered = ["text/html", "application/json"];
+    assert_eq!(media.best(None, &offered), Some("text/html"));
+    assert_eq!(
+        media.best(Some("application/*"), &offered),
+        Some("application/json")
+    );
+    assert_eq!(
+        media.best(Some("*/*;q=0.8, text/html;q=0.5"), &offered),
+        Some("application/json")
+    );
+    assert_eq!(
+        media.best(Some("*/*, text/html;q=0"), &offered),
+        Some("application/json")
+    );
+    assert_eq!(media.best(Some("image/png"), &offered), None);
+
+    // language prefixes
+    let language = Negotiation::Language;
+    let offered = ["en-US", "de-DE"];
+    assert_eq!(language.best(Some("de, en;q=0.8"), &offered), Some("de-DE"));
+    assert_eq!(language.best(Some("d, fr"), &offered), None);
+
+    // identity encoding acceptable unless excluded
+    let encoding = Negotiation::Encoding;
+    assert_eq!(
+        encoding.best(Some("br"), &["gzip", "identity"]),
+        Some("identity")
+    );
+    assert_eq!(
+        encoding.best(Some("gzip;q=0.5"), &["gzip", "identity"]),
+        Some("gzip")
+    );
+    assert_eq!(encoding.best(Some("*;q=0"), &["gzip", "identity"]), None);
+
+    // negotiate in handler or respond with 406
+    let address = serve(HttpSettings::new(), negotiate_handler);
+    let response = request_tcp(
+        address,
+        "GET / HTTP/1.1\r\nAccept: text/html;q=0.9, application/json\r\n\r\n",
+    );
+    assert!(response.ends_with("\r\n\r\napplication/json\r\n"));
+    let response = request_tcp(address, "GET / HTTP/1.1\r\nAccept: image/*\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 406 Not Acceptable\r\n"));
+}
+
+#[test]
+fn expect_continue() {
+    let settings = HttpSettings::new()
+        .max_body_size(100)
+        .continue_handler(Some(|_, url, headers| {
+            (url == "/forbidden" || headers.contains_key("x-reject"))
+                .then(|| respond("", "text/plain", ResponseData::forbidden().build()))
+        }));
+    let address = serve(settings, body_handler);
+
+    // 100 Continue before body is sent
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream
+        .write_all(b"POST / HTTP/1.1\r\nContent-Length: 5\r\nExpect: 100-continue\r\n\r\n")
+        .unwrap();
+    let mut interim = [0u8; 25];
+    stream.read_exact(&mut interim).unwrap();
+    assert_eq!(&interim, b"HTTP/1.1 100 Continue\r\n\r\n");
+    stream.write_all(b"hello").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("\r\n\r\nhello\r\n"));
+
+    // rejected early
+    let status = |request: &str| {
+        let response = request_tcp(address, request);
+        assert!(!response.contains("100 Continue"));
+        response.lines().next().unwrap().to_string()
+    };
+    assert_eq!(
+        status("POST / HTTP/1.1\r\nContent-Length: 101\r\nExpect: 100-continue\r\n\r\n"),
+        "HTTP/1.1 413 Content Too Large"
+    );
+    assert_eq!(
+        status("POST / HTTP/1.1\r\nContent-Length: 5\r\nExpect: something\r\n\r\n"),
+        "HTTP/1.1 417 Expectation Failed"
+    );
+    assert_eq!(
+        status("POST /forbidden HTTP/1.1\r\nContent-Length: 5\r\nExpect: 100-continue\r\n\r\n"),
+        "HTTP/1.1 403 Forbidden"
+    );
+    assert_eq!(
+        status(
+            "POST / HTTP/1.1\r\nContent-Length: 5\r\nX-Reject: 1\r\nExpect: 100-continue\r\n\r\n"
+        ),
+        "HTTP/1.1 403 Forbidden"
+    );
+}
+
+#[test]
+fn head_and_bodyless() {
+    let address = serve(HttpSettings::new(), status_handler);
+
+    // GET handler answers HEAD without body
+    let response = request_tcp(address, "GET / HTTP/1.1\r\n\r\n");
+    assert!(response.ends_with("\r\ncontent-length: 11\r\n\r\nGet false\r\n"));
+    let response = request_tcp(address, "HEAD / HTTP/1.1\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("\r\ncontent-length: 10\r\n"));
+    assert!(response.ends_with("\r\n\r\n"));
+
+    // no body and content-length for 204 and 304
+    for url in ["/raw", "/not-modified"] {
+        let response = request_tcp(address, &format!("GET {url} HTTP/1.1\r\n\r\n"));
+        assert!(!response.contains("content-length"));
+        assert!(response.ends_with("\r\n\r\n"));
+        assert!(!response.contains("content\r\n"));
+    }
+}
+
+#[test]
+fn redirect_response() {
+    // see other with location header
+    let response = TestResponse::parse(&redirect("/target?x=1")).unwrap();
+    assert_eq!(response.status(), 303);
+    assert_eq!(response.header("location"), Some("/target?x=1"));
+    assert!(response.text().contains("<a href=\"/target?x=1\">"));
+}
+
+#[test]
+fn test_harness() {
+    // handler only
+    let request = TestRequest::post("/upload?name=x")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("a=1");
+    let response = request.run(body_handler, &HttpSettings::new()).unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.reason(), "OK");
+    assert_eq!(
+        response.header("Content-Type"),
+        Some("text/plain; charset=utf-8")
+    );
+    assert_eq!(response.body(), b"a=1\r\n");
+    assert_eq!(response.text(), "a=1");
+
+    // raw request and peer address
+    let request = TestRequest::raw("GET / HTTP/1.1\r\nX-Real-IP: 10.0.0.1\r\n\r\n").unwrap();
+    let response = request.run(handler, &HttpSettings::new()).unwrap();
+    assert_eq!(response.text(), "10.0.0.1");
+    let request =
+        TestRequest::get("/").peer_addr(std::net::SocketAddr::from(([192, 168, 0, 1], 1234)));
+    assert_eq!(request.to_bytes(), b"GET / HTTP/1.1\r\n\r\n");
+    let response = request.run(handler, &HttpSettings::new()).unwrap();
+    assert_eq!(response.text(), "192.168.0.1");
+
+    // full stack with HEAD handling, date header and CORS
+    let server = TestServer::new(status_handler);
+    let response = server
+        .run(&TestRequest::new(HttpMethod::Head, "/"))
+        .unwrap();
+    assert_eq!(response.header("content-length"), Some("10"));
+    assert!(response.header("date").is_some());
+    assert!(response.body().is_empty());
+    let cors = CorsPolicy::new().any_origin();
+    let server =
+        TestServer::with_settings(HttpSettings::new().cors(Some(cors)), handler, |context| {
+            respond(context.error.to_string(), "text/plain", None)
+        });
+    let request = TestRequest::get("/").header("Origin", "https://example.com");
+    let response = server.run(&request).unwrap();
+    assert_eq!(response.header("Access-Control-Allow-Origin"), Some("*"));
+
+    // invalid responses
+    assert!(TestResponse::parse(b"HTTP/1.1 200 OK").is_err());
+    assert!(TestResponse::parse(b"HTTP/1.1 abc OK\r\n\r\n").is_err());
+}
+
+#[test]
+fn full_url() {
+    // full url of Host header or absolute-form
+    let url_handler =
+        |req: HttpRequest| Ok(respond(req.full_url().to_string(), "text/plain", None));
+    let request = TestRequest::get("/a/../b?q=1").header("Host", "Example.com:8080");
+    let response = request.run(url_handler, &HttpSettings::new()).unwrap();
+    assert_eq!(response.text(), "http://example.com:8080/b?q=1");
+    let request = TestRequest::raw("GET http://proxied.example/x?y HTTP/1.1\r\n\r\n").unwrap();
+    let response = request
+        .run(
+            |req| {
+                Ok(respond(
+                    format!("{} {}", req.url(), req.full_url()),
+                    "text/plain",
+                    None,
+                ))
+            },
+            &HttpSettings::new(),
+        )
+        .unwrap();
+    assert_eq!(response.text(), "/x http://proxied.example/x?y");
+
+    // empty Host header falls back to localhost, invalid one is rejected
+    let request = TestRequest::get("/x").header("Host", "");
+    let response = request.run(url_handler, &HttpSettings::new()).unwrap();
+    assert_eq!(response.text(), "http://localhost/x");
+    for host in [
+        "bad host", "a@b", "x/y", "x?y", "x#y", "x\\y", "x\ty", "x:port", "[::1",
+    ] {
+        let request = TestRequest::get("/").header("Host", host);
+        assert!(
+            request.run(url_handler, &HttpSettings::new()).is_err(),
+            "{host}"
+        );
+    }
+    let request = TestRequest::get("/").header("Host", "[::1]:8080");
+    let response = request.run(url_handler, &HttpSettings::new()).unwrap();
+    assert_eq!(response.text(), "http://[::1]:8080/");
+}
+
+#[test]
+fn metrics() {
+    let server = TestServer::with_settings(
+        HttpSettings::new().metrics_path(Some("/metrics")),
+        status_handler,
+        |context| respond(context.error.to_string(), "text/plain", None),
+    );
+    let metrics = server.metrics();
+
+    // requests by method and status class
+    server.run(&TestRequest::get("/")).unwrap();
+    server.run(&TestRequest::post("/").body("data")).unwrap();
+    server.run(&TestRequest::get("/not-modified")).unwrap();
+    server.run(&TestRequest::get("/error")).unwrap();
+    assert_eq!(metrics.requests(&HttpMethod::Get, 2), 2);
+    assert_eq!(metrics.requests(&HttpMethod::Post, 2), 1);
+    assert_eq!(metrics.requests(&HttpMethod::Get, 3), 1);
+    assert_eq!(metrics.requests(&HttpMethod::Get, 0), 0);
+    assert_eq!(metrics.requests(&HttpMethod::Get, u16::MAX), 0);
+    assert_eq!(metrics.requests_total(), 4);
+    assert_eq!(metrics.handler_errors(), 1);
+    assert_eq!(metrics.protocol_errors(), 0);
+    assert_eq!(metrics.latency().count(), 4);
+    assert_eq!(metrics.latency().buckets().last().unwrap().1, 4);
+    assert!(metrics.bytes_in() > 0 && metrics.bytes_out() > metrics.bytes_in());
+    assert_eq!(metrics.active_connections(), 0);
+
+    // Prometheus text format
+    let response = server.run(&TestRequest::get("/metrics")).unwrap();
+    assert_eq!(
+        response.header("content-type"),
+        Some("text/plain; version=0.0.4; charset=utf-8")
+    );
+    let text = response.text();
+    assert!(text.contains("kern_http_requests_total{method=\"GET\",status=\"2xx\"} 2\n"));
+    assert!(text.contains("kern_http_requests_total{method=\"GET\",status=\"3xx\"} 1\n"));
+    assert!(text.contains("# TYPE kern_http_request_duration_seconds histogram\n"));
+    assert!(text.contains("kern_http_request_duration_seconds_bucket{le=\"+Inf\"} 4\n"));
+    assert!(text.contains("kern_http_request_duration_seconds_count 4\n"));
+    assert!(text.contains("kern_http_handler_errors_total 1\n"));
+    assert!(text.contains("kern_http_protocol_errors_total 0\n"));
+
+    // errors before the handler are counted separately
+    let request = TestRequest::post("/").header("content-length", "abc");
+    server.run(&request).unwrap();
+    assert_eq!(metrics.handler_errors(), 1);
+    assert_eq!(metrics.protocol_errors(), 1);
+
+    // metrics disabled
+    let settings = HttpSettings::new()
+        .metrics_path(Some("/metrics"))
+        .metrics_path(None);
+    assert!(settings.metrics_path.is_none());
+    assert!(text.contains("kern_http_active_connections 0\n"));
+
+    // active connections of real server
+    let listener = Listener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = HttpServer::with_listener(
+        listener,
+        Arc::new(HttpSettings::new()),
+        handler,
+        |context| respond(context.error.to_string(), "text/plain", None),
+        #[cfg(feature = "tls")]
+        None,
+    )
+    .unwrap();
+    let stream = TcpStream::connect(address).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert_eq!(server.metrics().active_connections(), 1);
+    drop(stream);
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert_eq!(server.metrics().active_connections(), 0);
+
+    // connections closed without request are no errors
+    assert_eq!(server.metrics().protocol_errors(), 0);
+    request_tcp(address, "GET / HTTP/1.1\r\nContent-Length: x\r\n\r\n");
+    assert_eq!(server.metrics().protocol_errors(), 1);
+    assert_eq!(server.metrics().handler_errors(), 0);
+}
+
+#[test]
+fn request_id() {
+    let settings = HttpSettings::new()
+        .on_request_start(Some(|_| {
+            STARTED.fetch_add(1, Ordering::SeqCst);
+        }))
+        .on_request_end(Some(|summary| {
+            FINISHED.lock().unwrap().push(summary.clone())
+        }));
+    let server = TestServer::with_settings(settings, request_id_handler, |context| {
+        respond(context.error.to_string(), "text/plain", None)
+    });
+
+    // incoming X-Request-Id is used and echoed
+    let request = TestRequest::get("/").header("X-Request-Id", "abc-123");
+    let response = server.run(&request).unwrap();
+    assert_eq!(response.header("x-request-id"), Some("abc-123"));
+    assert_eq!(response.text(), "abc-123 Some(\"abc-123\")");
+    assert_eq!(current_request_id(), None);
+
+    // trace id of traceparent
+    let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+    let request = TestRequest::get("/").header("traceparent", traceparent);
+    let response = server.run(&request).unwrap();
+    assert_eq!(
+        response.header("x-request-id"),
+        Some("4bf92f3577b34da6a3ce929d0e0e4736")
+    );
+
+    // generated for missing or invalid ids
+    let request = TestRequest::get("/").header("X-Request-Id", "x".repeat(201));
+    let generated = server.run(&request).unwrap();
+    let generated = generated.header("x-request-id").unwrap();
+    assert_eq!(generated.len(), 32);
+    assert!(generated.bytes().all(|b| b.is_ascii_hexdigit()));
+    let response = server.run(&TestRequest::get("/")).unwrap();
+    assert_ne!(response.header("x-request-id"), Some(generated));
+
+    // start and end hooks
+    assert_eq!(STARTED.load(Ordering::SeqCst), 4);
+    let finished = FINISHED.lock().unwrap();
+    assert_eq!(finished.len(), 4);
+    assert_eq!(finished[0].id, "abc-123");
+    assert_eq!(finished[0].method, HttpMethod::Get);
+    assert_eq!(finished[0].status, 200);
+    assert_eq!(finished[0].ip, "127.0.0.1");
+    drop(finished);
+
+    // propagated by HttpClient
+    UPSTREAM
+        .set(serve(HttpSettings::new(), request_id_handler))
+        .unwrap();
+    let request = TestRequest::get("/forward").header("X-Request-Id", "forwarded");
+    let response = server.run(&request).unwrap();
+    assert!(response.text().starts_with("forwarded Some(\"forwarded\")"));
+}
+
+#[test]
+fn error_status() {
+    // internal errors are hidden by default
+    let server = TestServer::new(status_handler);
+    let response = server.run(&TestRequest::get("/error")).unwrap();
+    assert_eq!(response.status(), 500);
+    assert_eq!(response.text(), "Internal Server Error");
+    let response = server.run(&TestRequest::get("/teapot")).unwrap();
+    assert_eq!(response.status(), 418);
+    assert_eq!(response.text(), "short and stout");
+
+    // server-generated errors carry status
+    let request = TestRequest::raw("FOO / HTTP/1.1\r\n\r\n").unwrap();
+    assert_eq!(server.run(&request).unwrap().status(), 405);
+    let request = TestRequest::post("/").header("content-length", "abc");
+    let response = server.run(&request).unwrap();
+    assert_eq!(response.status(), 400);
+    assert_eq!(response.text(), "Content-Length is not of type usize");
+    let server = TestServer::with_settings(
+        HttpSettings::new().max_body_size(4),
+        status_handler,
+        |context| respond(context.reason(), "text/plain", None),
+    );
+    let response = server
+        .run(&TestRequest::post("/").body("too large"))
+        .unwrap();
+    assert_eq!(response.text(), "Content Too Large");
+    let listener = Listener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    HttpServer::with_listener(
+        listener,
+        Arc::new(HttpSettings::new().max_header_size(64)),
+        status_handler,
+        |context| {
+            let data = ResponseData::new().status(context.status).build();
+            respond(context.public_message(), "text/plain", data)
+        },
+        #[cfg(feature = "tls")]
+        None,
+    )
+    .unwrap();
+    let request = format!("GET / HTTP/1.1\r\nx-long: {}\r\n\r\n", "x".repeat(100));
+    let response = request_tcp(address, &request);
+    assert!(response.starts_with("HTTP/1.1 431 Request Header Fields Too Large"));
+    assert!(response.contains("Max header size exceeded"));
+
+    // header drip-fed in short reads is rejected
+    let mut stream = TcpStream::connect(address).unwrap();
+    for part in ["GET / ", "HTTP/1.1", "\r\nx: 1", "\r\ny: 2"] {
+        stream.write_all(part.as_bytes()).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok();
+    assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream
+        .write_all(b"POST / HTTP/1.1\r\ncontent-length: 10\r\n\r\n")
+        .unwrap();
+    for part in ["ab", "cd", "ef", "gh", "ij"] {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        stream.write_all(part.as_bytes()).ok();
+    }
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok();
+    assert!(response.contains("Read body failed too often"));
+
+    // request context of handler errors
+    let server = TestServer::with_settings(HttpSettings::new(), status_handler, |context| {
+        let request = context.request.as_ref().unwrap();
+        let body = format!("{} {} {}", context.code(), request.url, request.id);
+        respond(body, "text/plain", None)
+    });
+    let request = TestRequest::get("/error").header("X-Request-Id", "failed");
+    let response = server.run(&request).unwrap();
+    assert_eq!(response.text(), "500 /error failed");
+}
@@ -0,0 +1,43 @@
+#![cfg(unix)]
+
+use kern::http::server::Listener;
+use std::env::set_var;
+use std::net::{TcpListener, UdpSocket};
+use std::os::fd::IntoRawFd;
+use std::process;
+
+#[test]
+fn systemd() {
+    // SAFETY: only test in this binary, no other threads read the environment
+    unsafe { set_var("LISTEN_PID", process::id().to_string()) };
+
+    // overflowing or negative number of sockets
+    for fds in [i32::MAX.to_string(), "-1".to_string()] {
+        unsafe { set_var("LISTEN_FDS", fds) };
+        assert!(Listener::systemd().is_err());
+    }
+
+    // stream socket followed by datagram socket
+    let sockets = [
+        TcpListener::bind("127.0.0.1:0").unwrap().into_raw_fd(),
+        UdpSocket::bind("127.0.0.1:0").unwrap().into_raw_fd(),
+    ];
+    for (fd, socket) in (3..).zip(sockets) {
+        if socket != fd {
+            // SAFETY: file descriptors 3 and 4 are not used by the test harness
+            assert_eq!(unsafe { libc::dup2(socket, fd) }, fd);
+            unsafe { libc::close(socket) };
+        }
+    }
+    unsafe { set_var("LISTEN_FDS", "2") };
+    let err = Listener::systemd().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Socket passed by systemd is not a stream socket"
+    );
+
+    // all inherited file descriptors are closed
+    for fd in [3, 4] {
+        assert_eq!(unsafe { libc::fcntl(fd, libc::F_GETFD) }, -1);
+    }
+}
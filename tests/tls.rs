@@ -0,0 +1,286 @@
+#![cfg(feature = "tls")]
+
+use kern::Result;
+use kern::http::client::HttpClient;
+use kern::http::server::{
+    ClientAuth, ClientCertificate, HttpRequest, HttpServer, HttpSettings, Listener,
+    TlsConfigProvider, certificate_config, client_ca, load_certificate_provider, load_client_ca,
+    respond,
+};
+use rustls::{
+    ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned,
+};
+use std::fs::read;
+use std::io::prelude::*;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{sleep, spawn};
+use std::time::Duration;
+
+static RELOAD_ERRORS: AtomicUsize = AtomicUsize::new(0);
+
+fn certificate_handler(req: HttpRequest) -> Result<Vec<u8>> {
+    // describe client certificate
+    let body = match req.client_certificate() {
+        Some(cert) => format!(
+            "{} {} {}",
+            cert.common_name().unwrap_or_default(),
+            cert.subject_alt_names().join(","),
+            cert.fingerprint()
+        ),
+        None => "none".to_string(),
+    };
+    Ok(respond(body, "text/plain", None))
+}
+
+fn serve_tls(client_auth: ClientAuth) -> (SocketAddr, Arc<HttpServer>) {
+    // listen on random port with test certificates
+    let listener = Listener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let provider = TlsConfigProvider::from_files_with_client_auth(
+        "tests/certs/server.pem",
+        "tests/certs/server-key.pem",
+        client_auth,
+    )
+    .unwrap();
+    let server = HttpServer::with_listener(
+        listener,
+        Arc::new(HttpSettings::new()),
+        certificate_handler,
+        |context| respond(context.error.to_string(), "text/plain", None),
+        Some(provider),
+    )
+    .unwrap();
+    (address, server)
+}
+
+fn test_roots() -> RootCertStore {
+    // test CA as only trusted root
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut read("tests/certs/ca.pem").unwrap().as_slice()) {
+        roots.add(cert.unwrap()).unwrap();
+    }
+    roots
+}
+
+fn keep_alive_tls_server(connections: Arc<AtomicUsize>) -> SocketAddr {
+    // answer requests on same connection, TLS key update sent after each response
+    let raw_chain = read("tests/certs/server.pem").unwrap();
+    let chain = rustls_pemfile::certs(&mut raw_chain.as_slice())
+        .collect::<std::io::Result<Vec<_>>>()
+        .unwrap();
+    let raw_key = read("tests/certs/server-key.pem").unwrap();
+    let key = rustls_pemfile::private_key(&mut raw_key.as_slice())
+        .unwrap()
+        .unwrap();
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(chain, key)
+        .unwrap();
+    let config = Arc::new(config);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    spawn(move || {
+        for socket in listener.incoming() {
+            connections.fetch_add(1, Ordering::SeqCst);
+            let connection = ServerConnection::new(config.clone()).unwrap();
+            let mut stream = StreamOwned::new(connection, socket.unwrap());
+            spawn(move || {
+                let mut request = Vec::new();
+                let mut byte = [0u8];
+                while stream.read(&mut byte).unwrap_or(0) == 1 {
+                    request.push(byte[0]);
+                    if request.ends_with(b"\r\n\r\n") {
+                        request.clear();
+                        let response = b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+                        stream.write_all(response).unwrap();
+                        sleep(Duration::from_millis(50));
+                        stream.conn.refresh_traffic_keys().unwrap();
+                        stream.flush().unwrap();
+                    }
+                }
+            });
+        }
+    });
+    address
+}
+
+fn request_tls(address: SocketAddr, client_certificate: bool) -> std::io::Result<String> {
+    // trust test CA, optionally authenticate with client certificate
+    let builder = ClientConfig::builder().with_root_certificates(test_roots());
+    let config = match client_certificate {
+        true => {
+            let chain = rustls_pemfile::certs(&mut read("tests/certs/client.pem")?.as_slice())
+                .collect::<std::io::Result<Vec<_>>>()?;
+            let key =
+                rustls_pemfile::private_key(&mut read("tests/certs/client-key.pem")?.as_slice())?
+                    .unwrap();
+            builder.with_client_auth_cert(chain, key).unwrap()
+        }
+        false => builder.with_no_client_auth(),
+    };
+
+    // send request and read response until connection is closed
+    let connection =
+        ClientConnection::new(Arc::new(config), "localhost".try_into().unwrap()).unwrap();
+    let mut stream = StreamOwned::new(connection, TcpStream::connect(address)?);
+    stream.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")?;
+    let mut response = Vec::new();
+    match stream.read_to_end(&mut response) {
+        Err(err) if response.is_empty() => return Err(err),
+        _ => {}
+    }
+    Ok(String::from_utf8_lossy(&response).to_string())
+}
+
+#[test]
+fn reload() {
+    // load provider and reload from files
+    let provider = load_certificate_provider("examples/cert.pem", "examples/key.pem").unwrap();
+    let old_config = provider.config();
+    provider.reload().unwrap();
+    assert!(!Arc::ptr_eq(&old_config, &provider.config()));
+
+    // not modified since reload
+    assert!(!provider.reload_if_modified().unwrap());
+
+    // clones share the configuration
+    let clone = provider.clone();
+    provider.reload().unwrap();
+    assert!(Arc::ptr_eq(&clone.config(), &provider.config()));
+}
+
+#[test]
+fn watch() {
+    // watch copies of certificate and private key
+    let dir = std::env::temp_dir().join(format!("kern-watch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (cert, key) = (dir.join("cert.pem"), dir.join("key.pem"));
+    std::fs::copy("examples/cert.pem", &cert).unwrap();
+    std::fs::copy("examples/key.pem", &key).unwrap();
+    let provider =
+        load_certificate_provider(cert.to_str().unwrap(), key.to_str().unwrap()).unwrap();
+    let config = provider.config();
+    provider.watch(Duration::from_millis(20), |_| {
+        RELOAD_ERRORS.fetch_add(1, Ordering::SeqCst);
+    });
+
+    // broken certificate is reported and old config kept
+    std::thread::sleep(Duration::from_millis(50));
+    std::fs::write(&cert, "broken").unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(RELOAD_ERRORS.load(Ordering::SeqCst) > 0);
+    assert!(Arc::ptr_eq(&config, &provider.config()));
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn independent_providers() {
+    // every call creates a new provider
+    let first = load_certificate_provider("examples/cert.pem", "examples/key.pem").unwrap();
+    let second = load_certificate_provider("examples/cert.pem", "examples/key.pem").unwrap();
+    assert!(!Arc::ptr_eq(&first.config(), &second.config()));
+}
+
+#[test]
+fn set() {
+    // static provider can't be reloaded, but replaced
+    let raw_cert = read("examples/cert.pem").unwrap();
+    let raw_key = read("examples/key.pem").unwrap();
+    let provider = TlsConfigProvider::new(certificate_config(&raw_cert, &raw_key).unwrap());
+    assert!(provider.reload().is_err());
+
+    let new_config = certificate_config(&raw_cert, &raw_key).unwrap();
+    provider.set(new_config.clone());
+    assert!(Arc::ptr_eq(&new_config, &provider.config()));
+}
+
+#[test]
+fn client_certificate() {
+    // parse certificate chain
+    let raw_cert = read("examples/cert.pem").unwrap();
+    let chain = rustls_pemfile::certs(&mut raw_cert.as_slice())
+        .collect::<std::io::Result<Vec<_>>>()
+        .unwrap();
+    let cert = ClientCertificate::from_chain(&chain).unwrap();
+
+    // check subject and fingerprint
+    assert_eq!(cert.common_name(), Some("kern.localhost"));
+    assert_eq!(
+        cert.subject(),
+        "C=DE, ST=Niedersachsen, L=Hannover, O=Lennart Heinrich, OU=kern, CN=kern.localhost, emailAddress=kern@ltheinrich.de"
+    );
+    assert_eq!(
+        cert.fingerprint(),
+        "2e21d6d05e1d5bda91285c1c6f45c92761cfe4c807faa954591b29c71199f147"
+    );
+    assert!(cert.subject_alt_names().is_empty());
+    assert_eq!(cert.chain().len(), 1);
+}
+
+#[test]
+fn client_auth() {
+    // use certificate as trusted client CA
+    let roots = load_client_ca("examples/cert.pem").unwrap();
+    assert!(client_ca("").is_err());
+
+    // create providers requiring or requesting client certificates
+    TlsConfigProvider::from_files_with_client_auth(
+        "examples/cert.pem",
+        "examples/key.pem",
+        ClientAuth::Required(roots.clone()),
+    )
+    .unwrap()
+    .reload()
+    .unwrap();
+    TlsConfigProvider::from_files_with_client_auth(
+        "examples/cert.pem",
+        "examples/key.pem",
+        ClientAuth::Optional(roots),
+    )
+    .unwrap();
+}
+
+#[test]
+fn client_auth_handshake() {
+    // required certificate is passed to the request
+    let roots = load_client_ca("tests/certs/ca.pem").unwrap();
+    let (address, server) = serve_tls(ClientAuth::Required(roots.clone()));
+    let response = request_tls(address, true).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with(
+        "\r\n\r\nclient.kern.localhost DNS:client.kern.localhost,email:client@kern.localhost \
+         20f5858867aa24ad84e28cf7745e4bdd6158073df076ea5f1e8060546fdf6e6f\r\n"
+    ));
+
+    // client without certificate is rejected
+    assert!(request_tls(address, false).is_err());
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert_eq!(server.metrics().tls_handshake_failures(), 1);
+
+    // optional certificate
+    let (address, _) = serve_tls(ClientAuth::Optional(roots));
+    let response = request_tls(address, false).unwrap();
+    assert!(response.ends_with("\r\n\r\nnone\r\n"));
+    let response = request_tls(address, true).unwrap();
+    assert!(response.contains("client.kern.localhost"));
+}
+
+#[test]
+fn client_reuse() {
+    // TLS records received while idle don't close pooled connections
+    let connections = Arc::new(AtomicUsize::new(0));
+    let address = keep_alive_tls_server(connections.clone());
+    let config = ClientConfig::builder()
+        .with_root_certificates(test_roots())
+        .with_no_client_auth();
+    let client = HttpClient::new().with_tls_config(Arc::new(config));
+    for _ in 0..3 {
+        let response = client.get(format!("https://{address}/")).unwrap();
+        assert_eq!(response.body_text().unwrap(), "ok");
+        sleep(Duration::from_millis(200));
+    }
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+    assert_eq!(client.pool().idle(), 1);
+}